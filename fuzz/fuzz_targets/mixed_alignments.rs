@@ -0,0 +1,130 @@
+//! Threads performing random sequences of `empty_arc_slice`,
+//! `empty_arc_array`, and `clear_arc_cache` calls across many alignments.
+//!
+//! Double frees and use-after-frees are caught by the sanitizer the fuzzer
+//! runs under; `check` asserts the invariants which hold even while other
+//! threads are clearing the cache.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use empty_rc_slice::{
+    clear_arc_cache, empty_arc_array, empty_arc_slice, is_cached_arc,
+};
+use libfuzzer_sys::fuzz_target;
+use std::{any::Any, sync::Arc, thread};
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Slice(u8),
+    Array(u8),
+    Clear,
+    Drop(u8),
+}
+
+/// A handle returned from the cache, whose type is erased so that handles of
+/// every alignment can be kept together.
+struct Handle {
+    align: usize,
+    addr: usize,
+    _arc: Box<dyn Any + Send>,
+}
+
+fn check<T: Send + Sync + 'static>(arc: Arc<[T]>) -> Handle {
+    assert!(arc.is_empty());
+    assert!(arc.as_ptr().is_aligned());
+    // The caller's handle, plus possibly the cache's and other threads'.
+    assert!(Arc::strong_count(&arc) >= 1);
+    Handle {
+        align: align_of::<T>(),
+        addr: arc.as_ptr().addr(),
+        _arc: Box::new(arc),
+    }
+}
+
+macro_rules! aligned_types {
+    ($($name:ident($align:literal)),* $(,)?) => {
+        $(
+            #[repr(align($align))]
+            struct $name;
+        )*
+
+        const ALIGNMENTS: u8 = [$($align),*].len() as u8;
+
+        fn acquire(idx: u8, array: bool) -> Handle {
+            let mut i = 0;
+            $(
+                if idx % ALIGNMENTS == i {
+                    return if array {
+                        check::<$name>(empty_arc_array::<$name>())
+                    } else {
+                        check::<$name>(empty_arc_slice())
+                    };
+                }
+                i += 1;
+            )*
+            let _ = i;
+            unreachable!()
+        }
+
+        /// Checks that, with nobody else using the cache, every alignment is
+        /// shared by consecutive calls.
+        fn check_quiescent() {
+            $(
+                let a: Arc<[$name]> = empty_arc_slice();
+                let b: Arc<[$name]> = empty_arc_slice();
+                assert!(Arc::ptr_eq(&a, &b));
+                assert!(is_cached_arc(&a));
+            )*
+        }
+    };
+}
+
+aligned_types! {
+    Align1(1),
+    Align2(2),
+    Align4(4),
+    Align8(8),
+    Align16(16),
+    Align64(64),
+    Align4096(4096),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    threads: Vec<Vec<Op>>,
+}
+
+fn run(input: Input) {
+    let threads: Vec<_> = input
+        .threads
+        .into_iter()
+        .take(4)
+        .map(|ops| {
+            thread::spawn(move || {
+                let mut handles: Vec<Handle> = Vec::new();
+                for op in ops {
+                    match op {
+                        Op::Slice(idx) => handles.push(acquire(idx, false)),
+                        Op::Array(idx) => handles.push(acquire(idx, true)),
+                        Op::Clear => clear_arc_cache(),
+                        Op::Drop(idx) if !handles.is_empty() => {
+                            handles.swap_remove(idx as usize % handles.len());
+                        }
+                        Op::Drop(_) => {}
+                    }
+                }
+                handles
+            })
+        })
+        .collect();
+    let handles: Vec<Handle> =
+        threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+
+    for handle in &handles {
+        assert_eq!(handle.addr % handle.align, 0);
+    }
+    check_quiescent();
+}
+
+fuzz_target!(|input: Input| run(input));
@@ -0,0 +1,119 @@
+//! Zero-sized types for every possible alignment, for when the caches need to
+//! work with an alignment that is only known at runtime.
+
+/// A computation which is generic over a zero-sized type `A`, to be run with a
+/// type of some runtime-chosen alignment.
+pub(crate) trait WithAlign {
+    type Output;
+    fn call<A>(self) -> Self::Output;
+}
+
+/// The base-2 logarithm of the largest alignment Rust supports.
+pub(crate) const MAX_ALIGN_INDEX: usize = 29;
+
+/// The index of an alignment in a cache, i.e. its base-2 logarithm, which is
+/// known to be at most `MAX_ALIGN_INDEX`. Nothing using one needs a bounds
+/// check which could panic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct AlignIndex(usize);
+
+impl AlignIndex {
+    /// Returns `idx` as an index, or `None` if it is larger than
+    /// `MAX_ALIGN_INDEX`.
+    #[inline]
+    pub(crate) const fn new(idx: usize) -> Option<Self> {
+        if idx <= MAX_ALIGN_INDEX {
+            Some(Self(idx))
+        } else {
+            None
+        }
+    }
+
+    /// Every index, in increasing order.
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        (0..=MAX_ALIGN_INDEX).map(Self)
+    }
+
+    #[inline]
+    pub(crate) const fn get(self) -> usize {
+        self.0
+    }
+
+    /// Returns the element of `slots` for this index.
+    #[inline]
+    pub(crate) fn slot<S>(self, slots: &[S; MAX_ALIGN_INDEX + 1]) -> &S {
+        // Safety: `self.0 <= MAX_ALIGN_INDEX`, so it is in bounds.
+        unsafe { slots.get_unchecked(self.0) }
+    }
+}
+
+macro_rules! aligned_types {
+    ($($idx:literal => $name:ident($align:literal)),* $(,)?) => {
+        $(
+            #[repr(align($align))]
+            pub(crate) struct $name;
+        )*
+
+        /// Runs `f` with a zero-sized type whose alignment is `2^idx`.
+        pub(crate) fn with_align_index<F: WithAlign>(
+            idx: AlignIndex, f: F,
+        ) -> F::Output {
+            match idx.get() {
+                $($idx => f.call::<$name>(),)*
+                // Safety: an `AlignIndex` is at most `MAX_ALIGN_INDEX`, and
+                // every index up to that has an arm above.
+                _ => unsafe { core::hint::unreachable_unchecked() },
+            }
+        }
+    };
+}
+
+aligned_types! {
+    0 => Align0(1),
+    1 => Align1(2),
+    2 => Align2(4),
+    3 => Align3(8),
+    4 => Align4(16),
+    5 => Align5(32),
+    6 => Align6(64),
+    7 => Align7(128),
+    8 => Align8(256),
+    9 => Align9(512),
+    10 => Align10(1024),
+    11 => Align11(2048),
+    12 => Align12(4096),
+    13 => Align13(8192),
+    14 => Align14(16384),
+    15 => Align15(32768),
+    16 => Align16(65536),
+    17 => Align17(131072),
+    18 => Align18(262144),
+    19 => Align19(524288),
+    20 => Align20(1048576),
+    21 => Align21(2097152),
+    22 => Align22(4194304),
+    23 => Align23(8388608),
+    24 => Align24(16777216),
+    25 => Align25(33554432),
+    26 => Align26(67108864),
+    27 => Align27(134217728),
+    28 => Align28(268435456),
+    29 => Align29(536870912),
+}
+
+#[test]
+fn alignments() {
+    struct Check(usize);
+    impl WithAlign for Check {
+        type Output = ();
+        fn call<A>(self) {
+            assert_eq!(core::mem::size_of::<A>(), 0);
+            assert_eq!(core::mem::align_of::<A>(), 1 << self.0);
+        }
+    }
+    for idx in AlignIndex::all() {
+        with_align_index(idx, Check(idx.get()));
+    }
+    assert_eq!(AlignIndex::all().count(), MAX_ALIGN_INDEX + 1);
+    assert!(AlignIndex::new(MAX_ALIGN_INDEX + 1).is_none());
+}
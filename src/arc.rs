@@ -1,88 +1,1250 @@
+#[cfg(feature = "std")]
+extern crate std;
+
+// The lock backing the cache is the first of `spin`, `std-sync`, and
+// `wasm-single-thread` (on a wasm target without atomics) which is enabled,
+// and otherwise `parking_lot`, which the `arc` feature enables by default.
+#[cfg(all(
+    feature = "parking_lot",
+    not(any(
+        feature = "spin",
+        feature = "std-sync",
+        all(
+            feature = "wasm-single-thread",
+            target_family = "wasm",
+            not(target_feature = "atomics")
+        )
+    ))
+))]
 use parking_lot::RwLock;
+#[cfg(feature = "spin")]
+use spin::RwLock;
+
+#[cfg(not(any(
+    feature = "parking_lot",
+    feature = "spin",
+    feature = "std-sync",
+    all(
+        feature = "wasm-single-thread",
+        target_family = "wasm",
+        not(target_feature = "atomics")
+    )
+)))]
+compile_error!(
+    "the `arc` module requires one of the `parking_lot`, `spin`, or \
+     `std-sync` features, or `wasm-single-thread` on a wasm target without \
+     atomics"
+);
+
+/// `std`'s `RwLock`, ignoring poisoning. The cache's slots are only ever
+/// null or valid, so a panic while one of its guards was held cannot leave
+/// it inconsistent.
+#[cfg(all(feature = "std-sync", not(feature = "spin")))]
+struct RwLock<T>(std::sync::RwLock<T>);
+
+#[cfg(all(feature = "std-sync", not(feature = "spin")))]
+impl<T> RwLock<T> {
+    const fn new(value: T) -> Self {
+        Self(std::sync::RwLock::new(value))
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, T> {
+        self.0.read().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// A `RefCell` standing in for a lock on wasm targets without the atomics
+/// proposal, which cannot have more than one thread. Using a guard while
+/// `clear` holds the write guard, which would deadlock with a real lock,
+/// panics instead.
+#[cfg(all(
+    feature = "wasm-single-thread",
+    target_family = "wasm",
+    not(target_feature = "atomics"),
+    not(any(feature = "spin", feature = "std-sync"))
+))]
+struct RwLock<T>(core::cell::RefCell<T>);
+
+// Safety: without the atomics proposal, a wasm program has only one thread,
+// so this is never actually shared between threads.
+#[cfg(all(
+    feature = "wasm-single-thread",
+    target_family = "wasm",
+    not(target_feature = "atomics"),
+    not(any(feature = "spin", feature = "std-sync"))
+))]
+unsafe impl<T> Sync for RwLock<T> {}
+
+#[cfg(all(
+    feature = "wasm-single-thread",
+    target_family = "wasm",
+    not(target_feature = "atomics"),
+    not(any(feature = "spin", feature = "std-sync"))
+))]
+impl<T> RwLock<T> {
+    const fn new(value: T) -> Self {
+        Self(core::cell::RefCell::new(value))
+    }
 
-use alloc::{sync::Arc, vec, vec::Vec};
-use core::sync::atomic::{AtomicPtr, Ordering};
-
-/// Safety: `ptr` must only ever be initialized with an `Arc::into_raw`'d
-/// pointer to a `[U; 0]` with the same alignment as `T`.
-unsafe fn get_or_make_arc<T>(ptr: &AtomicPtr<()>) -> Arc<[T; 0]> {
-    let p = ptr.load(Ordering::Acquire) as *const [T; 0];
-    if !p.is_null() {
-        unsafe {
-            Arc::increment_strong_count(p);
-            return Arc::from_raw(p);
+    fn read(&self) -> core::cell::Ref<'_, T> {
+        self.0.borrow()
+    }
+
+    fn write(&self) -> core::cell::RefMut<'_, T> {
+        self.0.borrow_mut()
+    }
+}
+
+#[cfg(feature = "bstr")]
+use bstr::BStr;
+
+use crate::{
+    align::{AlignIndex, MAX_ALIGN_INDEX},
+    sealed::Sealed,
+    shared::{self, try_align_index, SharedPointer, Slot, WeakPointer},
+    AlignmentError, CacheStats,
+};
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+#[cfg(feature = "allocator_api")]
+use core::alloc::{AllocError, Allocator};
+use core::{
+    borrow::Borrow,
+    ffi::CStr,
+    mem::{ManuallyDrop, MaybeUninit},
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+#[cfg(feature = "std")]
+use std::{ffi::OsStr, path::Path, sync::OnceLock};
+
+/// The [`Arc`] family of pointers.
+enum ArcPointer {}
+
+impl SharedPointer for ArcPointer {
+    type Strong<T: ?Sized> = Arc<T>;
+
+    /// `Arc::new([])` would build the whole allocation on the stack first, and
+    /// with a large alignment it can be larger than the stack.
+    fn new_empty<A>() -> Arc<[A; 0]> {
+        let slice: Arc<[A]> = Arc::from(Vec::new());
+        if let Some(hook) = load_alloc_hook() {
+            hook(core::mem::align_of::<A>());
         }
+        // Safety: `slice` has length 0, so it has the same layout as `[A; 0]`.
+        unsafe { Arc::from_raw(Arc::into_raw(slice).cast::<[A; 0]>()) }
     }
-    let arc: Arc<[T; 0]> = Arc::new([]);
-    let raw = Arc::into_raw(arc.clone());
-    match ptr.compare_exchange(
-        core::ptr::null_mut(),
-        raw.cast_mut().cast(),
-        Ordering::AcqRel,
-        Ordering::Acquire,
-    ) {
-        Ok(_null) => arc,
-        Err(p) => {
-            unsafe {
-                drop(Arc::from_raw(raw));
+
+    fn into_raw<T: ?Sized>(this: Arc<T>) -> *const T {
+        Arc::into_raw(this)
+    }
+
+    unsafe fn from_raw<T>(ptr: *const T) -> Arc<T> {
+        unsafe { Arc::from_raw(ptr) }
+    }
+
+    unsafe fn increment_strong<T>(ptr: *const T) {
+        unsafe { Arc::increment_strong_count(ptr) }
+    }
+
+    unsafe fn decrement_strong<T>(ptr: *const T) {
+        unsafe { Arc::decrement_strong_count(ptr) }
+    }
+}
+
+impl WeakPointer for ArcPointer {
+    unsafe fn downgrade_raw<T>(ptr: *const T) -> *const T {
+        // The caller's strong reference, which must not be dropped.
+        let arc = ManuallyDrop::new(unsafe { Arc::from_raw(ptr) });
+        Weak::into_raw(Arc::downgrade(&arc))
+    }
+}
+
+/// Like `ArcPointer::new_empty`, but returns an error instead of aborting if
+/// the allocation fails. `Arc::try_new_uninit` does not build the allocation
+/// on the stack either.
+#[cfg(feature = "allocator_api")]
+fn try_new_empty<A>() -> Result<Arc<[A; 0]>, AllocError> {
+    let arc = Arc::<[A; 0]>::try_new_uninit()?;
+    if let Some(hook) = load_alloc_hook() {
+        hook(core::mem::align_of::<A>());
+    }
+    // Safety: `[A; 0]` has no bytes to initialize.
+    Ok(unsafe { arc.assume_init() })
+}
+
+// The orderings used for every atomic operation in this module. With the
+// `seqcst` feature they are all `SeqCst` instead.
+//
+// The default orderings are enough because each atomic is independent: no
+// code reasons about the order of operations on two different slots, so the
+// single total order `SeqCst` adds would not be relied on. What is relied on is
+// that a thread which loads a pointer from a slot sees the allocation it points
+// to fully initialized. Publishing with `Release` (as part of `AcqRel`) and
+// loading with `Acquire`, including on a failed `compare_exchange`, makes the
+// writes which initialized the allocation happen-before its use. `take` also
+// acquires, since the caller goes on to drop the reference it took.
+const ACQUIRE: Ordering =
+    if cfg!(feature = "seqcst") { Ordering::SeqCst } else { Ordering::Acquire };
+const RELEASE: Ordering =
+    if cfg!(feature = "seqcst") { Ordering::SeqCst } else { Ordering::Release };
+const ACQ_REL: Ordering =
+    if cfg!(feature = "seqcst") { Ordering::SeqCst } else { Ordering::AcqRel };
+
+impl Slot for AtomicPtr<()> {
+    fn load(&self) -> *const () {
+        self.load(ACQUIRE)
+    }
+
+    fn publish(&self, new: *const ()) -> Result<(), *const ()> {
+        self.compare_exchange(ptr::null_mut(), new.cast_mut(), ACQ_REL, ACQUIRE)
+            .map(drop)
+            .map_err(<*mut ()>::cast_const)
+    }
+
+    fn take(&self) -> *const () {
+        self.swap(ptr::null_mut(), ACQ_REL)
+    }
+}
+
+/// A cache of empty allocations for [`Arc`]s, with one allocation per
+/// alignment.
+///
+/// The free functions in this library use a global default cache, but
+/// separate caches can be created for parts of a program which should not
+/// share (or keep alive) allocations with the rest of it. Dropping a cache
+/// releases its references like [`clear`](EmptyArcCache::clear).
+pub struct EmptyArcCache {
+    // Each element is either null or an `into_raw`'d `Arc<[U; 0]>` where U's
+    // alignment is 2^index. Users must Arc::increment_strong_count the
+    // pointer they get *before* dropping their `lock` guard.
+    raws: [AtomicPtr<()>; SLOTS],
+    // Held shared while using a pointer from `raws`, and exclusively by
+    // `clear`, so that the cache's own reference is never released between
+    // someone loading the pointer and incrementing its count.
+    lock: RwLock<()>,
+}
+
+// No type can have an alignment above `2^MAX_ALIGN_INDEX`, so there is a slot
+// for every possible alignment and the cache never needs to grow.
+const SLOTS: usize = MAX_ALIGN_INDEX + 1;
+
+// With the `shared-symbol` feature, other copies of this library in the
+// process find this through `EXPORTED`.
+static RAWS: EmptyArcCache = EmptyArcCache::new();
+
+// The name of the lock backend, which is part of `SHARED_SYMBOL`, since
+// `EmptyArcCache`'s layout depends on it. Its number is part of `AbiTag`.
+//
+// Only `spin` and `std-sync` keep all of their state in the lock itself, so
+// only they work when the lock is shared with another copy of this library.
+// `parking_lot` parks waiters in a table global to each copy, where another
+// copy's unlock would never wake them. `shared-symbol` enables `std-sync`,
+// which takes precedence over `parking_lot`, and `wasm-single-thread` never
+// applies, since `shared-symbol` needs Unix.
+#[cfg(all(feature = "shared-symbol", feature = "spin"))]
+macro_rules! backend {
+    () => {
+        "spin"
+    };
+}
+#[cfg(all(
+    feature = "shared-symbol",
+    feature = "std-sync",
+    not(feature = "spin")
+))]
+macro_rules! backend {
+    () => {
+        "std_sync"
+    };
+}
+
+#[cfg(all(feature = "shared-symbol", feature = "spin"))]
+const BACKEND: u32 = 1;
+#[cfg(all(
+    feature = "shared-symbol",
+    feature = "std-sync",
+    not(feature = "spin")
+))]
+const BACKEND: u32 = 2;
+
+// The version and lock backend are part of the name, since only copies which
+// agree on both can agree on `EmptyArcCache`'s layout. `AbiTag` catches some
+// of the remaining differences, such as those between compilers.
+#[cfg(feature = "shared-symbol")]
+macro_rules! shared_symbol {
+    () => {
+        concat!("empty_rc_slice_arc_cache_v0_1_", backend!())
+    };
+}
+
+#[cfg(feature = "shared-symbol")]
+const SHARED_SYMBOL: &CStr =
+    match CStr::from_bytes_with_nul(concat!(shared_symbol!(), "\0").as_bytes())
+    {
+        Ok(name) => name,
+        Err(_) => panic!("`SHARED_SYMBOL` must not contain NUL"),
+    };
+
+/// Points to the default cache, and is exported under `SHARED_SYMBOL` so that
+/// other copies of this library in the process can find it. Its layout must
+/// not change without changing `SHARED_SYMBOL`.
+///
+/// The cache itself is not exported, since the dynamic loader can make each
+/// copy's references to an exported symbol point to another copy's instead.
+#[cfg(feature = "shared-symbol")]
+#[repr(C)]
+struct Exported {
+    abi: AbiTag,
+    cache: &'static EmptyArcCache,
+}
+
+/// Describes the `EmptyArcCache` an `Exported` points to. Another copy of
+/// this library only uses that cache if it describes its own the same way.
+#[cfg(feature = "shared-symbol")]
+#[repr(C)]
+#[derive(PartialEq, Eq)]
+struct AbiTag {
+    size: usize,
+    align: usize,
+    backend: u32,
+}
+
+#[cfg(feature = "shared-symbol")]
+impl AbiTag {
+    const CURRENT: Self = Self {
+        size: core::mem::size_of::<EmptyArcCache>(),
+        align: core::mem::align_of::<EmptyArcCache>(),
+        backend: BACKEND,
+    };
+}
+
+#[cfg(feature = "shared-symbol")]
+#[export_name = shared_symbol!()]
+static EXPORTED: Exported = Exported { abi: AbiTag::CURRENT, cache: &RAWS };
+
+#[cfg(all(feature = "shared-symbol", not(unix)))]
+compile_error!("the `shared-symbol` feature is only supported on Unix");
+
+/// Returns the cache of the first copy of this library exporting
+/// `SHARED_SYMBOL` which is visible to the whole process, or this copy's own
+/// `RAWS` if there is none.
+///
+/// The object holding another copy's cache is pinned with `RTLD_NODELETE`, so
+/// that unloading it with `dlclose` cannot leave the returned reference
+/// dangling. If it cannot be pinned, or its `AbiTag` differs from this copy's,
+/// this uses `RAWS` instead.
+#[cfg(feature = "shared-symbol")]
+fn shared_cache() -> &'static EmptyArcCache {
+    static SHARED: OnceLock<&'static EmptyArcCache> = OnceLock::new();
+    SHARED.get_or_init(|| {
+        // Safety: `SHARED_SYMBOL` is NUL-terminated.
+        let p =
+            unsafe { libc::dlsym(libc::RTLD_DEFAULT, SHARED_SYMBOL.as_ptr()) };
+        if p.is_null() {
+            return &RAWS;
+        }
+        let p = p.cast_const().cast::<Exported>();
+        // Safety: every copy of this library exporting `SHARED_SYMBOL`
+        // exports an `Exported`, whose `AbiTag` comes first with the same
+        // layout in each. Only the tag is read until it is known to match.
+        let abi = unsafe { &*ptr::addr_of!((*p).abi) };
+        if *abi != AbiTag::CURRENT {
+            return &RAWS;
+        }
+        // Safety: the tags match, so the other copy's `Exported` has the same
+        // layout as this one's.
+        let cache = unsafe { (*p).cache };
+        if ptr::eq(cache, &RAWS) {
+            return &RAWS;
+        }
+        // Safety: `Dl_info` is plain data, for which zeroes are valid.
+        let mut info: libc::Dl_info = unsafe { core::mem::zeroed() };
+        // Safety: `info` is valid for writes.
+        if unsafe { libc::dladdr(ptr::from_ref(cache).cast(), &mut info) } == 0
+            || info.dli_fname.is_null()
+        {
+            return &RAWS;
+        }
+        // Safety: `dli_fname` is a NUL-terminated path, and with
+        // `RTLD_NOLOAD` this only adds `RTLD_NODELETE` to the already loaded
+        // object, running no initializers. The handle is intentionally never
+        // closed.
+        let handle = unsafe {
+            libc::dlopen(
+                info.dli_fname,
+                libc::RTLD_LAZY | libc::RTLD_NOLOAD | libc::RTLD_NODELETE,
+            )
+        };
+        if handle.is_null() {
+            return &RAWS;
+        }
+        // The other copy's cache has the same layout as this one's, as far as
+        // `AbiTag` can tell, and enabling `shared-symbol` asserts that every
+        // copy exporting the symbol shares one allocator. The object holding
+        // it was pinned above, so it lives for the rest of the program.
+        cache
+    })
+}
+
+/// Returns the default cache, first registering it to be cleared at exit if
+/// the `atexit-cleanup` feature is enabled.
+///
+/// With the `shared-symbol` feature, this is the cache every copy of this
+/// library in the process agrees on, which need not be this copy's `RAWS`.
+#[inline]
+fn default_cache() -> &'static EmptyArcCache {
+    #[cfg(feature = "atexit-cleanup")]
+    {
+        static REGISTER: std::sync::Once = std::sync::Once::new();
+        REGISTER.call_once(|| {
+            extern "C" fn teardown() {
+                #[cfg(feature = "shared-symbol")]
+                shared_cache().clear();
+                #[cfg(not(feature = "shared-symbol"))]
+                RAWS.clear();
             }
+            // Safety: `teardown` does not unwind, and is valid for the rest of
+            // the program. If registering fails, the cache is just not cleared.
+            unsafe { libc::atexit(teardown) };
+        });
+    }
+    #[cfg(feature = "shared-symbol")]
+    return shared_cache();
+    #[cfg(not(feature = "shared-symbol"))]
+    &RAWS
+}
+
+// Either null or a `fn(usize)` set by `set_arc_alloc_hook`.
+static ALLOC_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+fn load_alloc_hook() -> Option<fn(usize)> {
+    let p = ALLOC_HOOK.load(ACQUIRE);
+    // Safety: `ALLOC_HOOK` only ever holds null or a `fn(usize)`.
+    (!p.is_null())
+        .then(|| unsafe { core::mem::transmute::<*mut (), fn(usize)>(p) })
+}
+
+// Either null or an `into_raw`'d `Arc<[u8; 1]>` holding a single NUL byte.
+static CSTR: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+// The layout of `OsStr` is unspecified on every platform, so it cannot be
+// reinterpreted from the empty byte slice and gets its own allocation.
+#[cfg(feature = "std")]
+static OS_STR: OnceLock<Arc<OsStr>> = OnceLock::new();
+
+// `Path` is a `repr(transparent)` wrapper around `OsStr`, but that is not a
+// documented guarantee, so this cannot reinterpret `OS_STR` either.
+#[cfg(feature = "std")]
+static PATH: OnceLock<Arc<Path>> = OnceLock::new();
+
+impl EmptyArcCache {
+    /// Creates a new, empty cache.
+    pub const fn new() -> Self {
+        Self {
+            raws: [const { AtomicPtr::new(ptr::null_mut()) }; SLOTS],
+            lock: RwLock::new(()),
+        }
+    }
+
+    /// Calls `f` with the slot for alignment `2^idx` while holding a shared
+    /// guard on `lock`.
+    fn with_slot<R>(
+        &self, idx: AlignIndex, f: impl FnOnce(&AtomicPtr<()>) -> R,
+    ) -> R {
+        let _guard = self.lock.read();
+        f(idx.slot(&self.raws))
+    }
+
+    /// Returns a new strong reference to the cached `Arc<[U; 0]>` where U's
+    /// alignment is `2^idx`, as from `Arc::into_raw`.
+    ///
+    /// This and the other by-index methods are not generic, so that they are
+    /// only compiled once no matter how many element types are used.
+    fn acquire_raw(&self, idx: AlignIndex) -> *const () {
+        // Safety: we hold the shared guard, so `clear` cannot release the
+        // cache's reference in the meantime.
+        self.with_slot(idx, |slot| unsafe {
+            shared::acquire::<ArcPointer>(slot, idx)
+        })
+    }
+
+    /// Returns a new weak reference to the cached `Arc<[U; 0]>` where U's
+    /// alignment is `2^idx`, as from `Weak::into_raw`.
+    fn acquire_weak_raw(&self, idx: AlignIndex) -> *const () {
+        // Safety: as above.
+        self.with_slot(idx, |slot| unsafe {
+            shared::acquire_weak::<ArcPointer>(slot, idx)
+        })
+    }
+
+    fn prewarm_index(&self, idx: AlignIndex) {
+        self.cached_raw(idx);
+    }
+
+    /// Returns the cache's own reference to the cached `Arc<[U; 0]>` where
+    /// U's alignment is `2^idx`, without incrementing it.
+    fn cached_raw(&self, idx: AlignIndex) -> *const () {
+        // Safety: `slot` is the slot for alignment `2^idx`.
+        self.with_slot(idx, |slot| unsafe {
+            shared::prewarm::<ArcPointer>(slot, idx)
+        })
+    }
+
+    fn prewarm_alignment(&self, align: usize) {
+        self.prewarm_index(shared::alignment_align_index(align));
+    }
+
+    /// Releases this cache's own reference to each of its allocations, so
+    /// that they are freed once every `Arc` pointing to them has been dropped.
+    /// Later calls will allocate anew.
+    pub fn clear(&self) {
+        let _guard = self.lock.write();
+        // Safety: we hold the write lock, so nobody else is using a pointer
+        // loaded from `raws`.
+        unsafe { shared::clear::<ArcPointer>(&self.raws) }
+    }
+
+    fn stats(&self) -> CacheStats {
+        shared::stats(&self.raws)
+    }
+
+    fn is_cached<T>(&self, arc: &Arc<[T]>) -> bool {
+        let Ok(idx) = try_align_index::<T>() else {
+            return false;
+        };
+        let p = Slot::load(idx.slot(&self.raws));
+        arc.is_empty() && !p.is_null() && Arc::as_ptr(arc).cast::<()>() == p
+    }
+
+    /// Returns an [`Arc`] which points to an empty array of `T`. This `Arc`
+    /// may or may not share an allocation with other `Arc`s returned from this
+    /// cache, including those pointing to other zero-sized types.
+    pub fn empty_array<T>(&self) -> Arc<[T; 0]> {
+        // An alignment with no slot, which no current compiler allows, gets a
+        // new uncached allocation with `no-panic` rather than panicking.
+        #[cfg(feature = "no-panic")]
+        return self
+            .try_empty_array()
+            .unwrap_or_else(|_| ArcPointer::new_empty());
+        #[cfg(not(feature = "no-panic"))]
+        self.try_empty_array().unwrap()
+    }
+
+    fn try_empty_array<T>(&self) -> Result<Arc<[T; 0]>, AlignmentError> {
+        let p = self.acquire_raw(try_align_index::<T>()?);
+        // Safety: `p` is an owned strong reference to an `Arc<[U; 0]>`, and
+        // `[U; 0]` has the same size and alignment as `[T; 0]`.
+        Ok(unsafe { Arc::from_raw(p.cast::<[T; 0]>()) })
+    }
+
+    /// Like `empty_array`, but returns an error instead of aborting if the
+    /// allocation for `T`'s alignment is not cached and cannot be made.
+    #[cfg(all(feature = "allocator_api", not(feature = "no-cache")))]
+    fn try_alloc_empty_array<T>(&self) -> Result<Arc<[T; 0]>, AllocError> {
+        let Ok(idx) = try_align_index::<T>() else {
+            return try_new_empty();
+        };
+        self.with_slot(idx, |slot| {
+            // Safety: `slot` is the slot for `T`'s alignment, and we hold the
+            // shared guard, so `clear` cannot release the cache's reference
+            // before it is incremented.
             unsafe {
-                let p = p as *const [T; 0];
-                // debug_assert!(p.is_aligned());
+                let p = shared::try_get_or_make::<ArcPointer, _, _>(
+                    slot,
+                    try_new_empty::<T>,
+                )?;
                 Arc::increment_strong_count(p);
-                Arc::from_raw(p)
+                Ok(Arc::from_raw(p))
             }
-        }
+        })
+    }
+
+    /// Returns an [`Arc`] which points to an empty slice of `T`. This `Arc`
+    /// may or may not share an allocation with other `Arc`s returned from this
+    /// cache, including those pointing to other zero-sized types.
+    #[inline]
+    pub fn empty_slice<T>(&self) -> Arc<[T]> {
+        self.empty_array()
+    }
+
+    fn empty_weak_array<T>(&self) -> Weak<[T; 0]> {
+        // No `Arc` can be cached for an alignment without a slot.
+        let Ok(idx) = try_align_index::<T>() else {
+            return Weak::new();
+        };
+        let p = self.acquire_weak_raw(idx);
+        // Safety: `p` is an owned weak reference to an `Arc<[U; 0]>`, and
+        // `[U; 0]` has the same size and alignment as `[T; 0]`.
+        unsafe { Weak::from_raw(p.cast::<[T; 0]>()) }
+    }
+}
+
+impl Default for EmptyArcCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for EmptyArcCache {
+    fn drop(&mut self) {
+        self.clear();
     }
 }
 
-// Each element is either null or an `into_raw`'d `Arc<[U; 0]>` where U's
-// alignment is 2^index. Users must Arc::increment_strong_count the
-// pointer they get *before* dropping their RwLock guard.
-static RAWS: RwLock<Vec<AtomicPtr<()>>> = RwLock::new(vec![]);
+/// Allocates and caches the empty allocation for `align` ahead of time, so that
+/// later calls for types with that alignment do not need to.
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of two, or is larger than any alignment
+/// Rust supports.
+pub fn prewarm_arc_alignment(align: usize) {
+    default_cache().prewarm_alignment(align)
+}
+
+/// Allocates and caches the empty allocation for `T`'s alignment ahead of
+/// time, so that later calls for types with that alignment do not need to.
+#[inline]
+pub fn prewarm_arc<T>() {
+    // There is nothing to prewarm for an alignment without a slot.
+    if let Ok(idx) = try_align_index::<T>() {
+        default_cache().prewarm_index(idx);
+    }
+}
+
+/// Releases this library's own reference to each cached empty slice, array,
+/// and string slice allocation, so that they are freed once every `Arc`
+/// pointing to them has been dropped. Later calls will allocate anew.
+///
+/// The empty C string, OS string, and path allocations are not affected.
+pub fn clear_arc_cache() {
+    default_cache().clear()
+}
+
+/// Returns statistics about the cache of empty slice, array, and string slice
+/// allocations.
+pub fn arc_cache_stats() -> CacheStats {
+    default_cache().stats()
+}
+
+/// Registers `hook` to be called with the alignment whenever a new empty
+/// array allocation is made for an [`Arc`], replacing any previous hook.
+///
+/// This covers every `Arc` cache, and every allocation made with the
+/// `no-cache` feature enabled. When two threads race to fill the same slot,
+/// both allocate, so the hook can be called more than once for an alignment.
+///
+/// The hook must not call this library's `Arc` functions, since it may run
+/// while the cache is in use.
+pub fn set_arc_alloc_hook(hook: fn(usize)) {
+    ALLOC_HOOK.store(hook as *mut (), RELEASE);
+}
+
+/// Unregisters the hook set by [`set_arc_alloc_hook`], returning it.
+pub fn take_arc_alloc_hook() -> Option<fn(usize)> {
+    let p = ALLOC_HOOK.swap(ptr::null_mut(), ACQ_REL);
+    // Safety: as in `load_alloc_hook`.
+    (!p.is_null())
+        .then(|| unsafe { core::mem::transmute::<*mut (), fn(usize)>(p) })
+}
+
+/// Returns the largest alignment which currently has a cached allocation, or
+/// `None` if the cache is empty.
+pub fn arc_cache_high_water() -> Option<usize> {
+    arc_cache_stats().highest_index.map(|idx| 1 << idx)
+}
+
+/// Returns whether `arc` points to the allocation currently cached for `T`'s
+/// alignment, i.e. whether it was returned by this library and the cache has
+/// not been cleared since.
+pub fn is_cached_arc<T>(arc: &Arc<[T]>) -> bool {
+    default_cache().is_cached(arc)
+}
 
 /// Returns an [`Arc`] which points to an empty array of `T`. This `Arc` may or
 /// may not share an allocation with other `Arc`s returned from this library,
 /// including those pointing to other zero-sized types.
+///
+/// With the `no-cache` feature enabled, this always makes a new allocation.
 pub fn empty_arc_array<T>() -> Arc<[T; 0]> {
-    let guard = RAWS.read();
-    let idx: usize = core::mem::align_of::<T>()
-        .ilog2()
-        .try_into()
-        .expect("alignment power should fit in usize");
-    match guard.get(idx) {
-        Some(ptr) => unsafe { get_or_make_arc::<T>(ptr) },
-        None => {
-            drop(guard);
-            let mut guard = RAWS.write();
-            if guard.len() <= idx {
-                guard.resize_with(idx + 1, || {
-                    AtomicPtr::new(core::ptr::null_mut())
-                });
-            }
-            let ptr = &guard[idx];
-            unsafe { get_or_make_arc::<T>(ptr) }
+    // As in `EmptyArcCache::empty_array`.
+    #[cfg(feature = "no-panic")]
+    return try_empty_arc_array().unwrap_or_else(|_| ArcPointer::new_empty());
+    #[cfg(not(feature = "no-panic"))]
+    try_empty_arc_array().unwrap()
+}
+
+/// Returns a pointer to the cached empty array of `T`, without making a new
+/// `Arc`, for passing across an FFI boundary.
+///
+/// The pointer is kept alive only by the cache's own strong reference, so it
+/// dangles once the cache is cleared by [`clear_arc_cache`] (or at exit, with
+/// the `atexit-cleanup` feature) unless an `Arc` to the same allocation is
+/// still alive. It is never null, and is aligned for `T`.
+///
+/// This uses the cache even with the `no-cache` feature enabled, since
+/// otherwise nothing would keep the allocation alive. For an alignment the
+/// cache has no slot for, which no current compiler allows, this leaks a new
+/// allocation instead.
+pub fn empty_arc_ptr<T>() -> ptr::NonNull<[T; 0]> {
+    let p = match try_align_index::<T>() {
+        Ok(idx) => default_cache().cached_raw(idx),
+        Err(AlignmentError) => {
+            Arc::into_raw(ArcPointer::new_empty::<T>()).cast()
         }
+    };
+    // Safety: the cache only holds pointers from `Arc::into_raw`.
+    unsafe { ptr::NonNull::new_unchecked(p.cast_mut().cast()) }
+}
+
+/// Returns a new [`Arc`] pointing to the allocation of a pointer returned by
+/// [`empty_arc_ptr`], incrementing its strong count.
+///
+/// # Safety
+///
+/// `ptr` must have been returned by [`empty_arc_ptr::<U>`](empty_arc_ptr) for
+/// some `U` with the same alignment as `T`, and its allocation must still be
+/// alive: the cache must not have been cleared since, or some `Arc` to the
+/// allocation must still exist.
+pub unsafe fn empty_arc_from_ptr<T>(ptr: ptr::NonNull<[T; 0]>) -> Arc<[T; 0]> {
+    let ptr = ptr.as_ptr().cast_const();
+    // Safety: `ptr` is from `Arc::into_raw` on an `Arc<[U; 0]>`, which has the
+    // same layout as `Arc<[T; 0]>`, and its allocation is alive.
+    unsafe {
+        Arc::increment_strong_count(ptr);
+        Arc::from_raw(ptr)
     }
 }
 
+/// Like [`empty_arc_array`], but for any `N` which is known to be zero, for
+/// use in code which is generic over the length of an array.
+///
+/// Using this with a non-zero `N` is a compile error.
+pub fn empty_arc_array_n<T, const N: usize>() -> Arc<[T; N]> {
+    const { assert!(N == 0, "`empty_arc_array_n` requires `N` to be 0") };
+    let arc: Arc<[T; 0]> = empty_arc_array();
+    // Safety: `N` is 0, so `[T; N]` is `[T; 0]`.
+    unsafe { Arc::from_raw(Arc::into_raw(arc).cast::<[T; N]>()) }
+}
+
+/// Like [`empty_arc_array`], but returns an error instead of panicking if
+/// `T`'s alignment is larger than any the cache has a slot for. That cannot
+/// happen with any alignment Rust currently supports.
+pub fn try_empty_arc_array<T>() -> Result<Arc<[T; 0]>, AlignmentError> {
+    #[cfg(feature = "no-cache")]
+    return Ok(ArcPointer::new_empty());
+    #[cfg(not(feature = "no-cache"))]
+    default_cache().try_empty_array()
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_arc_array`] would return. Only the weak count of the allocation is
+/// incremented, and since the allocation is kept alive by this library,
+/// upgrading the `Weak` succeeds unless [`clear_arc_cache`] has since been
+/// called.
+///
+/// This uses the cache even with the `no-cache` feature enabled, since
+/// otherwise nothing would keep the allocation alive.
+pub fn empty_arc_weak_array<T>() -> Weak<[T; 0]> {
+    default_cache().empty_weak_array()
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_arc_slice`] would return. Only the weak count of the allocation is
+/// incremented, and since the allocation is kept alive by this library,
+/// upgrading the `Weak` succeeds unless [`clear_arc_cache`] has since been
+/// called.
+#[inline]
+pub fn empty_arc_weak<T>() -> Weak<[T]> {
+    empty_arc_weak_array()
+}
+
+/// An unsized type which this library can make empty [`Arc`]s of.
+///
+/// This is implemented for `[T]`, `str`, `CStr`, and, with the relevant
+/// features, `OsStr`, `Path`, and `BStr`. It cannot be implemented outside
+/// this library.
+pub trait EmptyArcTarget: Sealed {
+    /// Returns an [`Arc`] which points to an empty `Self`, like the
+    /// `empty_arc_*` function for `Self`.
+    fn empty_arc() -> Arc<Self>;
+}
+
+impl<T> EmptyArcTarget for [T] {
+    #[inline]
+    fn empty_arc() -> Arc<[T]> {
+        empty_arc_array()
+    }
+}
+
+impl EmptyArcTarget for str {
+    fn empty_arc() -> Arc<str> {
+        let arc: Arc<[u8]> = empty_arc();
+        str_from_empty_bytes(arc)
+    }
+}
+
+/// Reinterprets an empty byte slice as a string slice, reusing its
+/// allocation.
+///
+/// The empty byte slice is always valid UTF-8, so this only checks the length.
+fn str_from_empty_bytes(arc: Arc<[u8]>) -> Arc<str> {
+    debug_assert!(arc.is_empty());
+    // Safety: `arc` is empty, so it is valid UTF-8, and `str` has the same
+    // layout as `[u8]`. This is the same conversion `alloc` uses for
+    // `From<&str> for Arc<str>`.
+    unsafe { Arc::from_raw(Arc::into_raw(arc) as *const str) }
+}
+
+/// Returns the empty C string allocation, kept alive by `CSTR`'s own
+/// reference.
+fn cached_cstr() -> *const [u8; 1] {
+    // Safety: `CSTR` only ever holds an `Arc<[u8; 1]>` holding a NUL byte.
+    unsafe { shared::get_or_make::<ArcPointer, _>(&CSTR, || Arc::new([0u8])) }
+}
+
+#[cfg(feature = "std")]
+fn cached_os_str() -> &'static Arc<OsStr> {
+    OS_STR.get_or_init(|| Arc::from(OsStr::new("")))
+}
+
+#[cfg(feature = "std")]
+fn cached_path() -> &'static Arc<Path> {
+    PATH.get_or_init(|| Arc::from(Path::new("")))
+}
+
+impl EmptyArcTarget for CStr {
+    fn empty_arc() -> Arc<CStr> {
+        #[cfg(feature = "no-cache")]
+        return Arc::from(c"");
+        #[cfg(not(feature = "no-cache"))]
+        {
+            let bytes: Arc<[u8]> = unsafe {
+                let p = cached_cstr();
+                Arc::increment_strong_count(p);
+                Arc::from_raw(p)
+            };
+            debug_assert!(CStr::from_bytes_with_nul(&bytes).is_ok());
+            // This is the same conversion `alloc` uses for
+            // `From<&CStr> for Arc<CStr>`.
+            unsafe { Arc::from_raw(Arc::into_raw(bytes) as *const CStr) }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl EmptyArcTarget for OsStr {
+    fn empty_arc() -> Arc<OsStr> {
+        #[cfg(feature = "no-cache")]
+        return Arc::from(OsStr::new(""));
+        #[cfg(not(feature = "no-cache"))]
+        cached_os_str().clone()
+    }
+}
+
+#[cfg(feature = "std")]
+impl EmptyArcTarget for Path {
+    fn empty_arc() -> Arc<Path> {
+        #[cfg(feature = "no-cache")]
+        return Arc::from(Path::new(""));
+        #[cfg(not(feature = "no-cache"))]
+        cached_path().clone()
+    }
+}
+
+#[cfg(feature = "bstr")]
+impl EmptyArcTarget for BStr {
+    fn empty_arc() -> Arc<BStr> {
+        let arc: Arc<[u8]> = empty_arc();
+        // `BStr` is documented to have the same representation as `[u8]`.
+        unsafe { Arc::from_raw(Arc::into_raw(arc) as *const BStr) }
+    }
+}
+
+/// Returns an [`Arc`] which points to an empty `U`, for use in code which is
+/// generic over the kind of empty value. This is the same as calling the
+/// `empty_arc_*` function for `U`.
+#[inline]
+pub fn empty_arc<U: ?Sized + EmptyArcTarget>() -> Arc<U> {
+    U::empty_arc()
+}
+
 /// Returns an [`Arc`] which points to an empty slice of `T`. This `Arc` may or
 /// may not share an allocation with other `Arc`s returned from this library,
 /// including those pointing to other zero-sized types.
 #[inline]
 pub fn empty_arc_slice<T>() -> Arc<[T]> {
-    empty_arc_array()
+    empty_arc()
 }
 
-/// Returns an [`Arc`] which points to an empty string slice. This `Arc` may or
-/// may not share an allocation with other `Arc`s returned from this library,
-/// including those pointing to other zero-sized types.
+/// Returns an [`Arc`] which points to a new, uncached empty slice of `T`, so
+/// that it is the only reference to its allocation.
+///
+/// The `Arc`s returned by [`empty_arc_slice`] are shared with the cache, so
+/// [`Arc::get_mut`] always fails on them and [`Arc::make_mut`] always clones.
+/// This one is unique until it is cloned, but it costs an allocation on every
+/// call, which the shared empties avoid.
+pub fn unique_empty_arc_slice<T>() -> Arc<[T]> {
+    ArcPointer::new_empty::<T>()
+}
+
+/// Like [`empty_arc_slice`], but returns an error instead of aborting if the
+/// allocation for `T`'s alignment is not cached yet and cannot be made.
+///
+/// Once the allocation for `T`'s alignment is cached, this never fails. With
+/// the `no-cache` feature enabled, it makes a new allocation every time, which
+/// can. This needs the `allocator_api` feature, since there is no stable way
+/// to allocate an `Arc` fallibly.
+#[cfg(feature = "allocator_api")]
+pub fn try_empty_arc_slice<T>() -> Result<Arc<[T]>, AllocError> {
+    #[cfg(feature = "no-cache")]
+    return Ok(try_new_empty::<T>()?);
+    #[cfg(not(feature = "no-cache"))]
+    Ok(default_cache().try_alloc_empty_array::<T>()?)
+}
+
+/// Returns an [`Arc`] which points to an empty slice of `T`, allocated with
+/// `alloc`.
+///
+/// Unlike [`empty_arc_slice`], this makes a new allocation every time, since
+/// an allocation cannot be shared between different allocators.
+#[cfg(feature = "allocator_api")]
+pub fn empty_arc_slice_in<T, A: Allocator>(alloc: A) -> Arc<[T], A> {
+    // As in `ArcPointer::new_empty`, `Arc::new_in([], alloc)` would build the
+    // whole allocation on the stack first.
+    let arc = Arc::<[T; 0], A>::new_uninit_in(alloc);
+    // Safety: `[T; 0]` has no bytes to initialize.
+    unsafe { arc.assume_init() }
+}
+
+/// Returns an [`Arc`] which points to an empty string slice, allocated with
+/// `alloc`.
+///
+/// Like [`empty_arc_slice_in`], this makes a new allocation every time.
+#[cfg(feature = "allocator_api")]
+pub fn empty_arc_str_in<A: Allocator>(alloc: A) -> Arc<str, A> {
+    let arc: Arc<[u8], A> = empty_arc_slice_in(alloc);
+    debug_assert!(arc.is_empty());
+    let (ptr, alloc) = Arc::into_raw_with_allocator(arc);
+    // Safety: as in `str_from_empty_bytes`.
+    unsafe { Arc::from_raw_in(ptr as *const str, alloc) }
+}
+
+/// Reinterprets an empty `Arc<[T]>` as an `Arc<[U]>` pointing to the same
+/// allocation, or returns `arc` unchanged if it is not empty.
+///
+/// Using this with types of different alignments is a compile error. The
+/// result shares an allocation with [`empty_arc_slice::<U>`](empty_arc_slice)
+/// if `arc` did with `empty_arc_slice::<T>`.
+pub fn cast_empty_arc<T, U>(arc: Arc<[T]>) -> Result<Arc<[U]>, Arc<[T]>> {
+    const {
+        assert!(
+            core::mem::align_of::<T>() == core::mem::align_of::<U>(),
+            "`cast_empty_arc` requires `T` and `U` to have the same alignment"
+        )
+    };
+    if !arc.is_empty() {
+        return Err(arc);
+    }
+    let p = Arc::into_raw(arc).cast::<U>();
+    // Safety: an empty `Arc<[T]>` has the same layout as an empty `Arc<[U]>`,
+    // since `T` and `U` have the same alignment, and there are no elements to
+    // reinterpret.
+    Ok(unsafe { Arc::from_raw(ptr::slice_from_raw_parts(p, 0)) })
+}
+
+/// Use this instead of `Arc::<[T]>::default()` for an empty slice which shares
+/// an allocation with every other one of its alignment.
+///
+/// This is the same as [`empty_arc_slice`]. `Arc::<[T]>::default()` may
+/// allocate every time, at least for larger alignments.
+///
+/// ```
+/// use empty_rc_slice::default_empty_arc_slice;
+/// use std::sync::Arc;
+///
+/// #[repr(align(64))]
+/// struct Align64;
+///
+/// let a: Arc<[Align64]> = Arc::default();
+/// let b: Arc<[Align64]> = Arc::default();
+/// assert!(!Arc::ptr_eq(&a, &b));
+///
+/// let a = default_empty_arc_slice::<Align64>();
+/// let b = default_empty_arc_slice::<Align64>();
+/// # #[cfg(not(feature = "no-cache"))]
+/// assert!(Arc::ptr_eq(&a, &b));
+/// ```
+#[inline]
+pub fn default_empty_arc_slice<T>() -> Arc<[T]> {
+    empty_arc_slice()
+}
+
+/// Returns an [`Arc`] which points to an empty slice of `MaybeUninit<T>`.
+/// Since `MaybeUninit<T>` has the same alignment as `T`, this shares an
+/// allocation with [`empty_arc_slice::<T>`](empty_arc_slice).
+#[inline]
+pub fn empty_arc_uninit_slice<T>() -> Arc<[MaybeUninit<T>]> {
+    empty_arc_slice()
+}
+
+/// Returns an [`Arc`] which points to an empty string slice. This comes from
+/// the same cache slot as [`empty_arc_slice::<u8>`](empty_arc_slice), so the
+/// two share an allocation and can be compared by address, unless the
+/// `no-cache` feature is enabled or [`clear_arc_cache`] is called between the
+/// two calls. It may or may not share an allocation with other `Arc`s returned
+/// from this library.
+#[inline]
 pub fn empty_arc_str() -> Arc<str> {
-    let arc: Arc<[u8]> = empty_arc_slice();
-    debug_assert!(core::str::from_utf8(&arc).is_ok());
-    unsafe { Arc::from_raw(Arc::into_raw(arc) as *const str) }
+    empty_arc()
+}
+
+/// Converts `bytes` into an `Arc<str>` with the same allocation if it is
+/// empty, and returns `None` otherwise.
+///
+/// Empty bytes are always valid UTF-8, so this never has to validate them.
+/// Passing the result of [`empty_arc_slice::<u8>`](empty_arc_slice) gives the
+/// same `Arc` as [`empty_arc_str`].
+#[inline]
+pub fn arc_str_from_empty_bytes(bytes: Arc<[u8]>) -> Option<Arc<str>> {
+    bytes.is_empty().then(|| str_from_empty_bytes(bytes))
+}
+
+/// Returns an [`Arc`] which points to an empty C string, i.e. a single NUL
+/// byte. This `Arc` may or may not share an allocation with other `Arc`s
+/// returned from this library.
+#[inline]
+pub fn empty_arc_cstr() -> Arc<CStr> {
+    empty_arc()
 }
 
+/// Returns an [`Arc`] which points to an empty OS string slice. This `Arc` may
+/// or may not share an allocation with other `Arc`s returned from this library.
+#[cfg(feature = "std")]
+#[inline]
+pub fn empty_arc_os_str() -> Arc<OsStr> {
+    empty_arc()
+}
+
+/// Returns an [`Arc`] which points to an empty path. This `Arc` may or may not
+/// share an allocation with other `Arc`s returned from this library.
+#[cfg(feature = "std")]
+#[inline]
+pub fn empty_arc_path() -> Arc<Path> {
+    empty_arc()
+}
+
+/// Returns an [`Arc`] which points to an empty byte string. This shares an
+/// allocation with [`empty_arc_slice::<u8>`](empty_arc_slice).
+#[cfg(feature = "bstr")]
+#[inline]
+pub fn empty_arc_bstr() -> Arc<BStr> {
+    empty_arc()
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_arc_str`] would return. Only the weak count of the allocation is
+/// incremented.
+pub fn empty_arc_weak_str() -> Weak<str> {
+    let weak: Weak<[u8]> = empty_arc_weak();
+    // Safety: the allocation is empty, so it is valid UTF-8, and `str` has the
+    // same layout as `[u8]`.
+    unsafe { Weak::from_raw(Weak::into_raw(weak) as *const str) }
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_arc_cstr`] would return. Only the weak count of the allocation is
+/// incremented, and since the allocation is never released by this library,
+/// upgrading the `Weak` always succeeds.
+pub fn empty_arc_weak_cstr() -> Weak<CStr> {
+    // Safety: the allocation is kept alive by `CSTR`'s own reference.
+    let bytes: Weak<[u8; 1]> =
+        unsafe { Weak::from_raw(ArcPointer::downgrade_raw(cached_cstr())) };
+    let bytes: Weak<[u8]> = bytes;
+    // As in `empty_arc_cstr`.
+    unsafe { Weak::from_raw(Weak::into_raw(bytes) as *const CStr) }
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_arc_os_str`] would return. Only the weak count of the allocation is
+/// incremented, and since the allocation is never released by this library,
+/// upgrading the `Weak` always succeeds.
+#[cfg(feature = "std")]
+pub fn empty_arc_weak_os_str() -> Weak<OsStr> {
+    Arc::downgrade(cached_os_str())
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_arc_path`] would return. Only the weak count of the allocation is
+/// incremented, and since the allocation is never released by this library,
+/// upgrading the `Weak` always succeeds.
+#[cfg(feature = "std")]
+pub fn empty_arc_weak_path() -> Weak<Path> {
+    Arc::downgrade(cached_path())
+}
+
+/// An `Arc<[T]>` which is known to be empty, for APIs which want to state that
+/// in their signatures.
+///
+/// This can only be made by this library, from [`empty_arc_slice`].
+#[derive(Clone)]
+pub struct EmptyArc<T> {
+    arc: Arc<[T]>,
+}
+
+impl<T> EmptyArc<T> {
+    /// Returns an empty slice from [`empty_arc_slice`].
+    #[inline]
+    pub fn new() -> Self {
+        Self { arc: empty_arc_slice() }
+    }
+
+    /// Returns the underlying `Arc`.
+    #[inline]
+    pub fn into_arc(self) -> Arc<[T]> {
+        self.arc
+    }
+}
+
+impl<T> Default for EmptyArc<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> core::ops::Deref for EmptyArc<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        &self.arc
+    }
+}
+
+impl<T> From<EmptyArc<T>> for Arc<[T]> {
+    #[inline]
+    fn from(empty: EmptyArc<T>) -> Self {
+        empty.into_arc()
+    }
+}
+
+impl<T> core::fmt::Debug for EmptyArc<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("EmptyArc")
+    }
+}
+
+/// Associated functions for making empty [`Arc`]s, so that they can be named
+/// through the type being made instead of imported separately.
+///
+/// This is implemented for `Arc<[T]>`, `Arc<[T; 0]>`, and `Arc<str>`, and
+/// cannot be implemented outside this library.
+///
+/// ```
+/// use empty_rc_slice::ArcExt;
+/// use std::sync::Arc;
+///
+/// let a = <Arc<[u32]>>::empty_slice();
+/// let b = <Arc<[u32]>>::empty_array();
+/// assert!(a.is_empty() && b.is_empty());
+/// assert_eq!(&*<Arc<str>>::empty_str(), "");
+/// ```
+pub trait ArcExt: Sealed {
+    /// The element type of the empty slices this makes.
+    type Elem;
+
+    /// Like [`empty_arc_slice`].
+    #[inline]
+    fn empty_slice() -> Arc<[Self::Elem]> {
+        empty_arc_slice()
+    }
+
+    /// Like [`empty_arc_array`].
+    #[inline]
+    fn empty_array() -> Arc<[Self::Elem; 0]> {
+        empty_arc_array()
+    }
+
+    /// Like [`empty_arc_str`]. This is only available on `Arc<str>`.
+    #[inline]
+    fn empty_str() -> Arc<str>
+    where
+        Self: Borrow<str>,
+    {
+        empty_arc_str()
+    }
+}
+
+impl<T> Sealed for Arc<[T]> {}
+impl<T> ArcExt for Arc<[T]> {
+    type Elem = T;
+}
+
+impl<T> Sealed for Arc<[T; 0]> {}
+impl<T> ArcExt for Arc<[T; 0]> {
+    type Elem = T;
+}
+
+impl Sealed for Arc<str> {}
+impl ArcExt for Arc<str> {
+    type Elem = u8;
+}
+
+/// Declares a `static` holding an empty `Arc<[T]>` for one type, so that
+/// later uses only clone it instead of going through the cache.
+///
+/// `empty_arc_static!(NAME: [T])` declares `static NAME`, whose `get` method
+/// returns an `Arc<[T]>`. The first call gets it from [`empty_arc_slice`], so
+/// it shares an allocation with the cache. `T` must be `Send + Sync`.
+///
+/// ```
+/// use empty_rc_slice::empty_arc_static;
+/// use std::sync::Arc;
+///
+/// empty_arc_static!(EMPTY_NAMES: [String]);
+///
+/// let names: Arc<[String]> = EMPTY_NAMES.get();
+/// assert!(names.is_empty());
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! empty_arc_static {
+    ($(#[$attr:meta])* $vis:vis $name:ident: [$t:ty]) => {
+        $(#[$attr])*
+        $vis static $name: $crate::__private::EmptyArcStatic<$t> =
+            $crate::__private::EmptyArcStatic::new();
+    };
+}
+
+/// The type of the statics declared by [`empty_arc_static!`].
+#[cfg(feature = "std")]
+pub struct EmptyArcStatic<T> {
+    arc: OnceLock<Arc<[T]>>,
+}
+
+#[cfg(feature = "std")]
+impl<T> EmptyArcStatic<T> {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        Self { arc: OnceLock::new() }
+    }
+
+    /// Returns a new reference to this static's empty slice.
+    #[inline]
+    pub fn get(&self) -> Arc<[T]> {
+        self.arc.get_or_init(empty_arc_slice).clone()
+    }
+}
+
+#[cfg(not(feature = "no-cache"))]
 #[test]
 fn works() {
     extern crate std;
@@ -102,3 +1264,522 @@ fn works() {
     let empty_str = empty_str.join().unwrap();
     assert!(std::ptr::eq(&u8[..], empty_str.as_bytes()));
 }
+
+#[cfg(all(feature = "no-panic", not(feature = "no-cache")))]
+#[test]
+fn no_panic_still_caches() {
+    let a: Arc<[u64; 0]> = empty_arc_array();
+    assert!(Arc::ptr_eq(&a, &empty_arc_array()));
+    let cache = EmptyArcCache::new();
+    assert!(Arc::ptr_eq(&cache.empty_array::<u64>(), &cache.empty_array()));
+}
+
+#[test]
+fn unique_is_unique() {
+    let mut a: Arc<[u8]> = unique_empty_arc_slice();
+    assert!(a.is_empty());
+    assert_eq!(Arc::strong_count(&a), 1);
+    assert!(Arc::get_mut(&mut a).is_some());
+    assert!(!is_cached_arc(&a));
+    #[cfg(not(feature = "no-cache"))]
+    {
+        let mut b: Arc<[u8]> = empty_arc_slice();
+        assert!(Arc::get_mut(&mut b).is_none());
+    }
+}
+
+#[test]
+fn str_is_valid() {
+    let s: Arc<str> = empty_arc_str();
+    assert_eq!(&*s, "");
+    assert!(core::str::from_utf8(s.as_bytes()).is_ok());
+}
+
+#[test]
+fn str_from_bytes() {
+    let bytes: Arc<[u8]> = empty_arc_slice();
+    let s = arc_str_from_empty_bytes(bytes.clone()).unwrap();
+    assert_eq!(&*s, "");
+    assert!(ptr::addr_eq(Arc::as_ptr(&s), Arc::as_ptr(&bytes)));
+    assert!(arc_str_from_empty_bytes(Arc::from(*b"abc")).is_none());
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn str_shares_with_bytes() {
+    let s: Arc<str> = empty_arc_str();
+    let b: Arc<[u8]> = empty_arc_slice();
+    assert!(ptr::addr_eq(Arc::as_ptr(&s), Arc::as_ptr(&b)));
+    assert_eq!(Arc::as_ptr(&s).cast::<u8>(), Arc::as_ptr(&b).cast::<u8>());
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn weak_upgrades_to_shared() {
+    let weak: Weak<[u32]> = empty_arc_weak();
+    let a: Arc<[u32]> = weak.upgrade().unwrap();
+    let b: Arc<[u32]> = empty_arc_slice();
+    assert!(Arc::ptr_eq(&a, &b));
+    let weak: Weak<[u32; 0]> = empty_arc_weak_array();
+    assert!(Arc::ptr_eq(&weak.upgrade().unwrap(), &empty_arc_array()));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn cstr() {
+    let a: Arc<CStr> = empty_arc_cstr();
+    assert!(a.to_bytes().is_empty());
+    assert_eq!(a.to_bytes_with_nul(), [0]);
+    let b: Arc<CStr> = empty_arc_cstr();
+    assert!(Arc::ptr_eq(&a, &b));
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn os_str() {
+    let a: Arc<OsStr> = empty_arc_os_str();
+    assert!(a.is_empty());
+    let b: Arc<OsStr> = empty_arc_os_str();
+    assert!(Arc::ptr_eq(&a, &b));
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn path() {
+    let a: Arc<Path> = empty_arc_path();
+    assert!(a.as_os_str().is_empty());
+    assert!(a.components().next().is_none());
+    let b: Arc<Path> = empty_arc_path();
+    assert!(Arc::ptr_eq(&a, &b));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn uninit_shares_with_init() {
+    let a: Arc<[MaybeUninit<u64>]> = empty_arc_uninit_slice();
+    let b: Arc<[u64]> = empty_arc_slice();
+    assert!(core::ptr::addr_eq(Arc::as_ptr(&a), Arc::as_ptr(&b)));
+}
+
+#[cfg(all(feature = "bstr", not(feature = "no-cache")))]
+#[test]
+fn bstr() {
+    let s: Arc<BStr> = empty_arc_bstr();
+    assert!(s.is_empty());
+    let b: Arc<[u8]> = empty_arc_slice();
+    assert!(core::ptr::addr_eq(Arc::as_ptr(&s), Arc::as_ptr(&b)));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn weak_str_upgrades_to_shared() {
+    let weak: Weak<str> = empty_arc_weak_str();
+    let a: Arc<str> = weak.upgrade().unwrap();
+    assert!(Arc::ptr_eq(&a, &empty_arc_str()));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn weak_cstr_upgrades_to_shared() {
+    let weak: Weak<CStr> = empty_arc_weak_cstr();
+    let a: Arc<CStr> = weak.upgrade().unwrap();
+    assert!(Arc::ptr_eq(&a, &empty_arc_cstr()));
+    assert_eq!(a.to_bytes_with_nul(), [0]);
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn weak_os_str_and_path_upgrade_to_shared() {
+    let weak: Weak<OsStr> = empty_arc_weak_os_str();
+    assert!(Arc::ptr_eq(&weak.upgrade().unwrap(), &empty_arc_os_str()));
+    let weak: Weak<Path> = empty_arc_weak_path();
+    assert!(Arc::ptr_eq(&weak.upgrade().unwrap(), &empty_arc_path()));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn stats() {
+    #[repr(align(256))]
+    struct Align256;
+
+    let _a: Arc<[u64]> = empty_arc_slice();
+    let _b: Arc<[Align256]> = empty_arc_slice();
+    // Other tests may populate the cache concurrently.
+    let stats = arc_cache_stats();
+    assert!(stats.slots >= 9);
+    assert!(stats.populated >= 2);
+    assert!(stats.highest_index >= Some(8));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn prewarm() {
+    #[repr(align(65536))]
+    struct Align65536;
+
+    prewarm_arc_alignment(65536);
+    prewarm_arc::<Align65536>();
+    let a: Arc<[Align65536]> = empty_arc_slice();
+    assert!(is_cached_arc(&a));
+    let stats = arc_cache_stats();
+    assert!(stats.populated >= 1);
+    assert!(stats.highest_index >= Some(16));
+}
+
+#[test]
+#[should_panic = "alignment must be a power of two"]
+fn prewarm_non_power_of_two() {
+    prewarm_arc_alignment(12);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn is_cached() {
+    use alloc::vec;
+
+    assert!(is_cached_arc::<u32>(&empty_arc_slice()));
+    assert!(!is_cached_arc::<u32>(&Arc::from(vec![])));
+    assert!(!is_cached_arc::<u32>(&Arc::from(vec![1, 2, 3])));
+}
+
+#[test]
+fn separate_caches() {
+    let a = EmptyArcCache::new();
+    let b = EmptyArcCache::default();
+    let x: Arc<[u32]> = a.empty_slice();
+    let y: Arc<[u32]> = b.empty_slice();
+    assert!(!Arc::ptr_eq(&x, &y));
+    assert!(Arc::ptr_eq(&x, &a.empty_slice()));
+    let z: Arc<[u32; 0]> = a.empty_array();
+    assert!(core::ptr::addr_eq(Arc::as_ptr(&x), Arc::as_ptr(&z)));
+    a.clear();
+    assert!(!Arc::ptr_eq(&x, &a.empty_slice()));
+    drop((a, b));
+    assert!(x.is_empty());
+}
+
+#[cfg(feature = "no-cache")]
+#[test]
+fn no_cache() {
+    let a: Arc<[u32]> = empty_arc_slice();
+    let b: Arc<[u32]> = empty_arc_slice();
+    assert!(!Arc::ptr_eq(&a, &b));
+    assert_eq!(Arc::strong_count(&a), 1);
+    assert!(!is_cached_arc(&a));
+
+    let c = empty_arc_cstr();
+    assert!(c.is_empty());
+    assert!(!Arc::ptr_eq(&c, &empty_arc_cstr()));
+    assert_eq!(Arc::strong_count(&c), 1);
+    #[cfg(feature = "std")]
+    {
+        let o = empty_arc_os_str();
+        assert!(o.is_empty());
+        assert!(!Arc::ptr_eq(&o, &empty_arc_os_str()));
+        let p = empty_arc_path();
+        assert!(p.as_os_str().is_empty());
+        assert!(!Arc::ptr_eq(&p, &empty_arc_path()));
+    }
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn stress() {
+    extern crate std;
+    use std::{collections::BTreeSet, thread, vec::Vec};
+
+    #[repr(align(4096))]
+    struct Align4096;
+
+    fn addrs() -> [usize; 5] {
+        [
+            Arc::as_ptr(&empty_arc_slice::<u8>()).addr(),
+            Arc::as_ptr(&empty_arc_slice::<u16>()).addr(),
+            Arc::as_ptr(&empty_arc_slice::<u64>()).addr(),
+            Arc::as_ptr(&empty_arc_slice::<u128>()).addr(),
+            Arc::as_ptr(&empty_arc_slice::<Align4096>()).addr(),
+        ]
+    }
+
+    let iterations = if cfg!(miri) { 10 } else { 1000 };
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            thread::spawn(move || {
+                (0..iterations).map(|_| addrs()).collect::<BTreeSet<_>>()
+            })
+        })
+        .collect();
+    let mut seen = BTreeSet::new();
+    for thread in threads {
+        seen.extend(thread.join().unwrap());
+    }
+    // Every thread saw the same allocation for each alignment every time.
+    assert_eq!(seen.len(), 1);
+}
+
+#[cfg(all(feature = "std-sync", not(feature = "spin")))]
+#[test]
+fn recovers_from_poison() {
+    // A local cache, so that clearing it does not race with the tests of the
+    // default cache.
+    let cache = EmptyArcCache::new();
+    let result = std::panic::catch_unwind(|| {
+        let _guard = cache.lock.0.write().unwrap();
+        panic!("poisoning the cache's lock");
+    });
+    assert!(result.is_err());
+    assert!(cache.lock.0.is_poisoned());
+
+    let a: Arc<[u32]> = cache.empty_slice();
+    assert_eq!(a.len(), 0);
+    assert!(Arc::ptr_eq(&a, &cache.empty_slice()));
+    cache.clear();
+    assert_eq!(cache.empty_slice::<u32>().len(), 0);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn shares_per_alignment() {
+    #[repr(align(1024))]
+    struct Align1024;
+
+    fn check<T>() {
+        let a: Arc<[T]> = empty_arc_slice();
+        let b: Arc<[T]> = empty_arc_slice();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(Arc::as_ptr(&a).cast::<T>().is_aligned());
+    }
+
+    check::<u8>();
+    check::<u16>();
+    check::<u32>();
+    check::<u64>();
+    check::<u128>();
+    check::<Align1024>();
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn shares_across_types() {
+    #[repr(align(8))]
+    struct Align8;
+
+    let a: Arc<[u64]> = empty_arc_slice();
+    let b: Arc<[Align8]> = empty_arc_slice();
+    let c: Weak<[f64]> = empty_arc_weak();
+    assert_eq!(Arc::as_ptr(&a).addr(), Arc::as_ptr(&b).addr());
+    assert_eq!(Arc::as_ptr(&a).addr(), c.as_ptr().addr());
+    assert!(is_cached_arc(&c.upgrade().unwrap()));
+}
+
+#[test]
+fn huge_alignment() {
+    #[repr(align(1048576))]
+    struct Align1M;
+
+    let a: Arc<[Align1M]> = empty_arc_slice();
+    assert!(Arc::as_ptr(&a).cast::<Align1M>().is_aligned());
+    // The cache's own size does not depend on the alignments used.
+    assert_eq!(arc_cache_stats().slots, MAX_ALIGN_INDEX + 1);
+}
+
+#[cfg(all(test, loom))]
+impl Slot for loom::sync::atomic::AtomicPtr<()> {
+    fn load(&self) -> *const () {
+        self.load(ACQUIRE)
+    }
+
+    fn publish(&self, new: *const ()) -> Result<(), *const ()> {
+        self.compare_exchange(ptr::null_mut(), new.cast_mut(), ACQ_REL, ACQUIRE)
+            .map(drop)
+            .map_err(<*mut ()>::cast_const)
+    }
+
+    fn take(&self) -> *const () {
+        self.swap(ptr::null_mut(), ACQ_REL)
+    }
+}
+
+#[cfg(loom)]
+#[test]
+fn loom_concurrent_first_use() {
+    use loom::sync::{atomic::AtomicPtr, Arc as LoomArc};
+
+    fn acquire(slots: &[AtomicPtr<()>; SLOTS]) -> Arc<[u64; 0]> {
+        let idx = try_align_index::<u64>().unwrap();
+        // Safety: `idx.slot(slots)` is the slot for alignment `2^idx`, and is
+        // not cleared until both threads are done.
+        let p = unsafe { shared::acquire::<ArcPointer>(idx.slot(slots), idx) };
+        unsafe { Arc::from_raw(p.cast::<[u64; 0]>()) }
+    }
+
+    loom::model(|| {
+        let slots = LoomArc::new(core::array::from_fn::<_, SLOTS, _>(|_| {
+            AtomicPtr::new(ptr::null_mut())
+        }));
+        let other = {
+            let slots = slots.clone();
+            loom::thread::spawn(move || acquire(&slots))
+        };
+        let a = acquire(&slots);
+        let b = other.join().unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        // The losing thread freed its own allocation, and the winner's is
+        // held by the slot and both handles.
+        assert_eq!(Arc::strong_count(&a), 3);
+
+        // Safety: nobody is using a pointer loaded from `slots`.
+        unsafe { shared::clear::<ArcPointer>(&slots) };
+        assert_eq!(Arc::strong_count(&a), 2);
+    });
+}
+
+#[test]
+fn try_array() {
+    #[repr(align(536870912))]
+    struct AlignMax;
+
+    let a: Arc<[u32; 0]> = try_empty_arc_array().unwrap();
+    assert_eq!(a.len(), 0);
+    // The largest alignment Rust supports still has a slot.
+    assert!(try_align_index::<AlignMax>().is_ok());
+}
+
+#[cfg(all(feature = "allocator_api", not(feature = "no-cache")))]
+#[test]
+fn try_slice() {
+    #[repr(align(32))]
+    struct Align32;
+
+    let a: Arc<[Align32]> = try_empty_arc_slice().unwrap();
+    assert!(is_cached_arc(&a));
+    assert!(Arc::ptr_eq(&a, &try_empty_arc_slice().unwrap()));
+}
+
+/// An allocator which counts its live allocations, for testing the
+/// allocator-aware functions.
+#[cfg(all(test, feature = "allocator_api"))]
+struct Counting<'a>(&'a core::cell::Cell<usize>);
+
+#[cfg(all(test, feature = "allocator_api"))]
+unsafe impl Allocator for Counting<'_> {
+    fn allocate(
+        &self, layout: core::alloc::Layout,
+    ) -> Result<ptr::NonNull<[u8]>, core::alloc::AllocError> {
+        self.0.set(self.0.get() + 1);
+        alloc::alloc::Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(
+        &self, ptr: ptr::NonNull<u8>, layout: core::alloc::Layout,
+    ) {
+        self.0.set(self.0.get() - 1);
+        unsafe { alloc::alloc::Global.deallocate(ptr, layout) }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+#[test]
+fn slice_in() {
+    #[repr(align(1048576))]
+    struct Align1M;
+
+    let live = core::cell::Cell::new(0);
+    let a: Arc<[u64], _> = empty_arc_slice_in(Counting(&live));
+    assert!(a.is_empty());
+    assert_eq!(live.get(), 1);
+    drop(a);
+    assert_eq!(live.get(), 0);
+
+    // Too large to build on the stack first.
+    let a: Arc<[Align1M], _> = empty_arc_slice_in(Counting(&live));
+    assert!(Arc::as_ptr(&a).cast::<Align1M>().is_aligned());
+    drop(a);
+    assert_eq!(live.get(), 0);
+}
+
+#[cfg(feature = "allocator_api")]
+#[test]
+fn str_in() {
+    let live = core::cell::Cell::new(0);
+    let s: Arc<str, Counting<'_>> = empty_arc_str_in(Counting(&live));
+    assert_eq!(&*s, "");
+    assert!(ptr::eq(Arc::allocator(&s).0, &live));
+    assert_eq!(live.get(), 1);
+    drop(s);
+    assert_eq!(live.get(), 0);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn generic() {
+    let a: Arc<[u32]> = empty_arc::<[u32]>();
+    assert!(Arc::ptr_eq(&a, &empty_arc_slice()));
+    let s: Arc<str> = empty_arc::<str>();
+    assert!(Arc::ptr_eq(&s, &empty_arc_str()));
+    assert!(empty_arc::<CStr>().is_empty());
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn array_n() {
+    fn generic<const N: usize>() -> Arc<[u16; N]> {
+        empty_arc_array_n()
+    }
+    let a: Arc<[u16; 0]> = generic::<0>();
+    assert!(Arc::ptr_eq(&a, &empty_arc_array()));
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn static_macro() {
+    #[repr(align(512))]
+    struct Align512;
+
+    crate::empty_arc_static!(EMPTY: [Align512]);
+
+    let a = EMPTY.get();
+    assert!(Arc::ptr_eq(&a, &EMPTY.get()));
+    assert!(Arc::ptr_eq(&a, &empty_arc_slice()));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn ptr_round_trip() {
+    #[repr(align(2048))]
+    struct Align2048;
+
+    let p = empty_arc_ptr::<Align2048>();
+    assert!(p.is_aligned());
+    let a = unsafe { empty_arc_from_ptr(p) };
+    assert!(Arc::ptr_eq(&a, &empty_arc_array()));
+    assert_eq!(Arc::as_ptr(&a), p.as_ptr().cast_const());
+    // At least the cache's own reference and `a`.
+    assert!(Arc::strong_count(&a) >= 2);
+    drop(a);
+    assert_eq!(empty_arc_ptr::<Align2048>(), p);
+}
+
+#[test]
+fn cast() {
+    let a: Arc<[i32]> = empty_arc_slice();
+    let p = Arc::as_ptr(&a).cast::<()>();
+    let b: Arc<[u32]> = cast_empty_arc(a).unwrap();
+    assert_eq!(Arc::as_ptr(&b).cast::<()>(), p);
+    let c: Arc<[i32]> = cast_empty_arc(b).unwrap();
+    assert_eq!(Arc::as_ptr(&c).cast::<()>(), p);
+
+    let full: Arc<[i32]> = Arc::new([1, 2]);
+    let full = cast_empty_arc::<_, u32>(full).unwrap_err();
+    assert_eq!(*full, [1, 2]);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn empty_arc_newtype() {
+    let e = EmptyArc::<u8>::new();
+    assert!(e.is_empty());
+    let a = e.clone().into_arc();
+    assert!(is_cached_arc(&a));
+    assert!(Arc::ptr_eq(&a, &Arc::from(e)));
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::boxed::Box;
+use core::{
+    ffi::CStr,
+    ptr::{self, NonNull},
+};
+#[cfg(feature = "std")]
+use std::{ffi::OsStr, path::Path};
+
+/// Returns a [`Box`] which points to an empty array of `T`. This does not
+/// allocate.
+#[inline]
+pub fn empty_box_array<T>() -> Box<[T; 0]> {
+    let ptr = NonNull::<[T; 0]>::dangling().as_ptr();
+    // Safety: `[T; 0]` is zero-sized, so a dangling, well-aligned pointer is
+    // valid for a `Box`, and dropping it does not deallocate.
+    unsafe { Box::from_raw(ptr) }
+}
+
+/// Returns a [`Box`] which points to an empty slice of `T`. This does not
+/// allocate.
+#[inline]
+pub fn empty_box_slice<T>() -> Box<[T]> {
+    let ptr =
+        ptr::slice_from_raw_parts_mut(NonNull::<T>::dangling().as_ptr(), 0);
+    // Safety: a zero-length slice is zero-sized, so a dangling, well-aligned
+    // pointer is valid for a `Box`, and dropping it does not deallocate.
+    unsafe { Box::from_raw(ptr) }
+}
+
+/// Returns a [`Box`] which points to an empty string slice. This does not
+/// allocate.
+#[inline]
+pub fn empty_box_str() -> Box<str> {
+    let b: Box<[u8]> = empty_box_slice();
+    // Safety: the empty byte slice is valid UTF-8, and `str` has the same
+    // layout as `[u8]`.
+    unsafe { Box::from_raw(Box::into_raw(b) as *mut str) }
+}
+
+/// Returns a [`Box`] which points to an empty C string, i.e. a single NUL
+/// byte. Unlike the other empty boxes, this has to allocate.
+#[inline]
+pub fn empty_box_cstr() -> Box<CStr> {
+    Box::from(c"")
+}
+
+/// Returns a [`Box`] which points to an empty OS string slice.
+#[cfg(feature = "std")]
+#[inline]
+pub fn empty_box_os_str() -> Box<OsStr> {
+    // The layout of `OsStr` is unspecified, so this cannot reinterpret
+    // `empty_box_slice`.
+    Box::from(OsStr::new(""))
+}
+
+/// Returns a [`Box`] which points to an empty path.
+#[cfg(feature = "std")]
+#[inline]
+pub fn empty_box_path() -> Box<Path> {
+    // The layout of `Path` is unspecified, so this cannot reinterpret
+    // `empty_box_slice`.
+    Box::from(Path::new(""))
+}
+
+#[test]
+fn works() {
+    #[repr(align(4096))]
+    struct Page;
+
+    fn check<T>() {
+        let b: Box<[T]> = empty_box_slice();
+        assert!(b.is_empty());
+        // No allocation was made; the box just holds a dangling pointer.
+        assert_eq!(b.as_ptr(), NonNull::dangling().as_ptr());
+        let a: Box<[T; 0]> = empty_box_array();
+        assert!(ptr::eq(&a[..], &b[..]));
+    }
+    check::<u8>();
+    check::<u16>();
+    check::<u64>();
+    check::<alloc::string::String>();
+    check::<Page>();
+}
+
+#[test]
+fn empty_str() {
+    let s = empty_box_str();
+    assert!(s.is_empty());
+    assert_eq!(s.len(), 0);
+    let raw = Box::into_raw(s);
+    let s = unsafe { Box::from_raw(raw) };
+    assert_eq!(s.len(), 0);
+    assert_eq!(s.as_ptr(), NonNull::dangling().as_ptr());
+}
+
+#[test]
+fn cstr() {
+    let s = empty_box_cstr();
+    assert!(s.to_bytes().is_empty());
+    assert_eq!(s.to_bytes_with_nul(), [0]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn os_str_and_path() {
+    assert!(empty_box_os_str().is_empty());
+    let p = empty_box_path();
+    assert!(p.as_os_str().is_empty());
+    assert!(p.components().next().is_none());
+}
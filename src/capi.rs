@@ -0,0 +1,60 @@
+//! C entry points for getting the cached empty allocations across an FFI
+//! boundary.
+//!
+//! Every pointer these return holds one strong reference to its allocation,
+//! which the caller must give back to the matching release function exactly
+//! once.
+
+use crate::empty_arc_slice;
+use alloc::sync::Arc;
+
+/// Writes a pointer to the cached empty byte slice to `*out_ptr`, and its
+/// length (always 0) to `*out_len`.
+///
+/// The pointer is never null, and is also valid as an empty UTF-8 string. It
+/// must not be read through, and must be passed to
+/// [`empty_arc_u8_slice_release`] once the caller is done with it.
+///
+/// In C:
+///
+/// ```c
+/// void empty_arc_u8_slice(const uint8_t **out_ptr, size_t *out_len);
+/// ```
+///
+/// # Safety
+///
+/// `out_ptr` and `out_len` must be valid for writes and properly aligned.
+#[no_mangle]
+pub unsafe extern "C" fn empty_arc_u8_slice(
+    out_ptr: *mut *const u8, out_len: *mut usize,
+) {
+    let arc: Arc<[u8]> = empty_arc_slice();
+    let len = arc.len();
+    let ptr = Arc::into_raw(arc).cast::<u8>();
+    // Safety: the caller guarantees both are valid for writes.
+    unsafe {
+        out_ptr.write(ptr);
+        out_len.write(len);
+    }
+}
+
+/// Releases the strong reference held by a pointer from
+/// [`empty_arc_u8_slice`].
+///
+/// In C:
+///
+/// ```c
+/// void empty_arc_u8_slice_release(const uint8_t *ptr);
+/// ```
+///
+/// # Safety
+///
+/// `ptr` must have been written by [`empty_arc_u8_slice`], and must not have
+/// been released already.
+#[no_mangle]
+pub unsafe extern "C" fn empty_arc_u8_slice_release(ptr: *const u8) {
+    // Safety: `ptr` is from `Arc::into_raw` on an `Arc<[u8]>` of length 0,
+    // which has the same layout as `Arc<[u8; 0]>`, and still holds its
+    // reference.
+    unsafe { Arc::decrement_strong_count(ptr.cast::<[u8; 0]>()) }
+}
@@ -1,15 +1,167 @@
 #![no_std]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 #![deny(unsafe_op_in_unsafe_fn)]
 
 extern crate alloc;
 
-#[cfg(feature = "arc")]
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+mod align;
+#[cfg(feature = "arc-module")]
 mod arc;
-#[cfg(feature = "rc")]
+#[cfg(feature = "box")]
+mod boxed;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "rc-module")]
 mod rc;
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+mod sealed;
+#[cfg(feature = "serde")]
+mod serde_arc;
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+mod shared;
+#[cfg(feature = "test-util")]
+mod test_util;
+#[cfg(feature = "triomphe")]
+mod triomphe_arc;
 
-#[cfg(feature = "arc")]
-pub use arc::{empty_arc_array, empty_arc_slice, empty_arc_str};
+/// A snapshot of the state of one of this library's caches of empty
+/// allocations.
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CacheStats {
+    /// The number of alignment slots the cache has space for.
+    pub slots: usize,
+    /// The number of slots which currently hold an allocation.
+    pub populated: usize,
+    /// The index of the highest populated slot, i.e. the base-2 logarithm of
+    /// the largest alignment with a cached allocation.
+    pub highest_index: Option<usize>,
+}
 
-#[cfg(feature = "rc")]
-pub use rc::{empty_rc_array, empty_rc_slice, empty_rc_str};
+/// The error returned when a type's alignment is larger than any this
+/// library's caches have a slot for.
+///
+/// This cannot currently happen, since the caches cover every alignment Rust
+/// supports, but a future compiler might allow larger alignments.
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AlignmentError;
+
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+impl core::fmt::Display for AlignmentError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("alignment is larger than any the cache supports")
+    }
+}
+
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+impl core::error::Error for AlignmentError {}
+
+#[cfg(any(feature = "arc-module", feature = "rc-module"))]
+pub use shared::alignment_index;
+
+/// Returns an empty slice of `T` which lives forever. This does not allocate,
+/// and every call for the same `T` returns the same address.
+///
+/// This is not backed by the `Arc` cache, since `clear_arc_cache` can free its
+/// allocations, which would leave the slice dangling. A zero-length slice needs
+/// no allocation to be valid, so it points to `T`'s alignment instead, as
+/// [`NonNull::dangling`](core::ptr::NonNull::dangling) does.
+#[inline]
+pub fn empty_static_slice<T>() -> &'static [T] {
+    let ptr = core::ptr::NonNull::<T>::dangling().as_ptr();
+    // Safety: a zero-length slice is zero-sized, so a dangling, well-aligned
+    // pointer is valid for it for any lifetime.
+    unsafe { core::slice::from_raw_parts(ptr, 0) }
+}
+
+#[cfg(feature = "arc-module")]
+pub use arc::{
+    arc_cache_high_water, arc_cache_stats, arc_str_from_empty_bytes,
+    cast_empty_arc, clear_arc_cache, default_empty_arc_slice, empty_arc,
+    empty_arc_array, empty_arc_array_n, empty_arc_cstr, empty_arc_from_ptr,
+    empty_arc_ptr, empty_arc_slice, empty_arc_str, empty_arc_uninit_slice,
+    empty_arc_weak, empty_arc_weak_array, empty_arc_weak_cstr,
+    empty_arc_weak_str, is_cached_arc, prewarm_arc, prewarm_arc_alignment,
+    set_arc_alloc_hook, take_arc_alloc_hook, try_empty_arc_array,
+    unique_empty_arc_slice, ArcExt, EmptyArc, EmptyArcCache, EmptyArcTarget,
+};
+
+#[cfg(all(feature = "arc-module", feature = "std"))]
+pub use arc::{
+    empty_arc_os_str, empty_arc_path, empty_arc_weak_os_str,
+    empty_arc_weak_path,
+};
+
+#[cfg(all(feature = "arc-module", feature = "bstr"))]
+pub use arc::empty_arc_bstr;
+
+#[cfg(all(feature = "arc-module", feature = "allocator_api"))]
+pub use arc::{empty_arc_slice_in, empty_arc_str_in, try_empty_arc_slice};
+
+#[cfg(feature = "serde")]
+pub use serde_arc::deserialize_empty_arc_slice;
+
+#[cfg(feature = "test-util")]
+pub use test_util::CountingAllocator;
+
+#[cfg(feature = "triomphe")]
+pub use triomphe_arc::{
+    empty_triomphe_arc_slice, empty_triomphe_arc_str, empty_triomphe_thin_arc,
+};
+
+#[cfg(feature = "box")]
+pub use boxed::{
+    empty_box_array, empty_box_cstr, empty_box_slice, empty_box_str,
+};
+
+#[cfg(all(feature = "box", feature = "std"))]
+pub use boxed::{empty_box_os_str, empty_box_path};
+
+#[cfg(feature = "rc-module")]
+pub use rc::{
+    clear_rc_cache, default_empty_rc_slice, empty_rc, empty_rc_array,
+    empty_rc_cstr, empty_rc_from_ptr, empty_rc_ptr, empty_rc_slice,
+    empty_rc_str, empty_rc_uninit_slice, empty_rc_weak, empty_rc_weak_array,
+    empty_rc_weak_cstr, empty_rc_weak_str, is_cached_rc, prewarm_rc,
+    prewarm_rc_alignment, rc_cache_stats, unique_empty_rc_slice, EmptyRc,
+    EmptyRcCache, EmptyRcTarget, RcExt,
+};
+
+#[cfg(all(feature = "rc-module", feature = "std"))]
+pub use rc::{
+    empty_rc_os_str, empty_rc_path, empty_rc_weak_os_str, empty_rc_weak_path,
+};
+
+#[cfg(all(feature = "rc-module", feature = "bstr"))]
+pub use rc::empty_rc_bstr;
+
+#[cfg(all(feature = "rc-module", feature = "allocator_api"))]
+pub use rc::{empty_rc_slice_in, try_empty_rc_slice};
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(all(feature = "arc-module", feature = "std"))]
+    pub use crate::arc::EmptyArcStatic;
+    #[cfg(all(feature = "rc-module", feature = "std"))]
+    pub use crate::rc::{EmptyRcStatic, EmptyRcStaticCell};
+    #[cfg(all(feature = "rc-module", feature = "std"))]
+    pub use std::thread_local;
+
+    #[cfg(all(feature = "rc-module", feature = "std"))]
+    extern crate std;
+}
+
+#[test]
+fn static_slice() {
+    #[repr(align(64))]
+    struct Align64;
+
+    let a: &'static [u64] = empty_static_slice();
+    assert!(a.is_empty());
+    assert_eq!(a.as_ptr(), empty_static_slice::<u64>().as_ptr());
+    assert!(empty_static_slice::<Align64>().as_ptr().is_aligned());
+}
@@ -1,47 +1,580 @@
+#[cfg(feature = "std")]
 extern crate std;
 
-use alloc::{rc::Rc, vec, vec::Vec};
-use core::cell::RefCell;
+#[cfg(not(any(feature = "std", feature = "critical-section")))]
+compile_error!(
+    "the `rc` module requires either the `std` or `critical-section` feature"
+);
+
+use crate::{
+    align::{AlignIndex, MAX_ALIGN_INDEX},
+    sealed::Sealed,
+    shared::{self, try_align_index, SharedPointer, Slot, WeakPointer},
+    AlignmentError, CacheStats,
+};
+use alloc::{
+    rc::{Rc, Weak},
+    vec::Vec,
+};
+#[cfg(feature = "bstr")]
+use bstr::BStr;
+#[cfg(feature = "allocator_api")]
+use core::alloc::{AllocError, Allocator};
+#[cfg(feature = "std")]
+use core::cell::OnceCell;
+use core::{
+    borrow::Borrow,
+    cell::Cell,
+    ffi::CStr,
+    mem::{ManuallyDrop, MaybeUninit},
+    ptr::NonNull,
+};
+#[cfg(feature = "std")]
 use std::thread_local;
+#[cfg(feature = "std")]
+use std::{ffi::OsStr, path::Path};
+
+/// The [`Rc`] family of pointers.
+enum RcPointer {}
+
+impl SharedPointer for RcPointer {
+    type Strong<T: ?Sized> = Rc<T>;
+
+    /// `Rc::new([])` would build the whole allocation on the stack first, and
+    /// with a large alignment it can be larger than the stack.
+    fn new_empty<A>() -> Rc<[A; 0]> {
+        let slice: Rc<[A]> = Rc::from(Vec::new());
+        // Safety: `slice` has length 0, so it has the same layout as `[A; 0]`.
+        unsafe { Rc::from_raw(Rc::into_raw(slice).cast::<[A; 0]>()) }
+    }
+
+    fn into_raw<T: ?Sized>(this: Rc<T>) -> *const T {
+        Rc::into_raw(this)
+    }
+
+    unsafe fn from_raw<T>(ptr: *const T) -> Rc<T> {
+        unsafe { Rc::from_raw(ptr) }
+    }
+
+    unsafe fn increment_strong<T>(ptr: *const T) {
+        unsafe { Rc::increment_strong_count(ptr) }
+    }
+
+    unsafe fn decrement_strong<T>(ptr: *const T) {
+        unsafe { Rc::decrement_strong_count(ptr) }
+    }
+}
+
+impl WeakPointer for RcPointer {
+    unsafe fn downgrade_raw<T>(ptr: *const T) -> *const T {
+        // The caller's strong reference, which must not be dropped.
+        let rc = ManuallyDrop::new(unsafe { Rc::from_raw(ptr) });
+        Weak::into_raw(Rc::downgrade(&rc))
+    }
+}
+
+/// Like `RcPointer::new_empty`, but returns an error instead of aborting if
+/// the allocation fails. `Rc::try_new_uninit` does not build the allocation on
+/// the stack either.
+#[cfg(feature = "allocator_api")]
+fn try_new_empty<A>() -> Result<Rc<[A; 0]>, AllocError> {
+    let rc = Rc::<[A; 0]>::try_new_uninit()?;
+    // Safety: `[A; 0]` has no bytes to initialize.
+    Ok(unsafe { rc.assume_init() })
+}
+
+// Every slot only ever holds allocations made on its own thread, since the
+// caches are `!Send` and `!Sync`.
+impl Slot for Cell<*const ()> {
+    fn load(&self) -> *const () {
+        self.get()
+    }
+
+    fn publish(&self, new: *const ()) -> Result<(), *const ()> {
+        let p = self.get();
+        if p.is_null() {
+            self.set(new);
+            Ok(())
+        } else {
+            Err(p)
+        }
+    }
+
+    fn take(&self) -> *const () {
+        self.replace(core::ptr::null())
+    }
+}
+
+/// A cache of empty allocations for [`Rc`]s, with one allocation per
+/// alignment.
+///
+/// The free functions in this library use a thread-local default cache, but
+/// separate caches can be created for parts of a program which should not
+/// share (or keep alive) allocations with the rest of it. Dropping a cache
+/// releases its references like [`clear`](EmptyRcCache::clear).
+pub struct EmptyRcCache {
+    // Each element is either null or an `into_raw`'d `Rc<[U; 0]>` where U's
+    // alignment is 2^index. Users must Rc::increment_strong_count the
+    // pointer they get *before* calling back into the cache.
+    raws: [Cell<*const ()>; SLOTS],
+}
+
+// No type can have an alignment above `2^MAX_ALIGN_INDEX`, so there is a slot
+// for every possible alignment and the cache never needs to grow.
+const SLOTS: usize = MAX_ALIGN_INDEX + 1;
 
-/// Safety: `ptr` must only ever be initialized with an `Rc::into_raw`'d
-/// pointer to a `[U; 0]` with the same alignment as `T`, allocated on the
-/// current thread.
-unsafe fn get_or_make_rc<T>(ptr: &mut *const ()) -> Rc<[T; 0]> {
-    let p = *ptr as *const [T; 0];
-    if !p.is_null() {
-        unsafe {
-            Rc::increment_strong_count(p);
-            return Rc::from_raw(p);
+// The default cache is dropped when the thread exits, releasing its
+// references. Any `Rc`s still pointing to its allocations keep them alive as
+// usual, since they can only be dropped on this thread too.
+#[cfg(feature = "std")]
+thread_local! {
+    static RAWS: EmptyRcCache = const { EmptyRcCache::new() };
+}
+
+/// Either null or an `into_raw`'d `Rc<[u8; 1]>` holding a single NUL byte.
+/// Like `EmptyRcCache`, dropping it releases its reference, so that the
+/// default one is freed when its thread exits.
+struct CStrSlot(Cell<*const ()>);
+
+impl Drop for CStrSlot {
+    fn drop(&mut self) {
+        let p = Slot::take(&self.0).cast::<[u8; 1]>();
+        if !p.is_null() {
+            // Safety: the slot held its own strong reference, and nothing can
+            // use it once it is being dropped.
+            unsafe { drop(Rc::from_raw(p)) }
         }
     }
-    let rc: Rc<[T; 0]> = Rc::new([]);
-    let raw = Rc::into_raw(rc.clone());
-    *ptr = raw.cast();
-    rc
 }
-// Each element is either null or an `into_raw`'d `Rc<[U; 0]>` where U's
-// alignment is 2^index. Users must Rc::increment_strong_count the
-// pointer they get *before* dropping their RefCell guard.
+
+#[cfg(feature = "std")]
 thread_local! {
-    static RAWS: RefCell<Vec<*const ()>> = const { RefCell::new(vec![]) };
+    static CSTR: CStrSlot = const { CStrSlot(Cell::new(core::ptr::null())) };
+}
+
+/// Stands in for a thread-local on targets without `std`, by assuming that
+/// the whole program runs on a single thread. Accesses are additionally
+/// wrapped in a critical section.
+#[cfg(not(feature = "std"))]
+struct SingleThread<T>(T);
+
+// Safety: enabling the `critical-section` feature without `std` asserts that
+// the program is single-threaded, so this is never actually shared between
+// threads.
+#[cfg(not(feature = "std"))]
+unsafe impl<T> Sync for SingleThread<T> {}
+
+#[cfg(not(feature = "std"))]
+impl<T> SingleThread<T> {
+    fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        critical_section::with(|_| f(&self.0))
+    }
+
+    /// Never fails, since a global is never destroyed. This matches
+    /// `LocalKey::try_with`.
+    fn try_with<R>(
+        &'static self, f: impl FnOnce(&T) -> R,
+    ) -> Result<R, core::convert::Infallible> {
+        Ok(self.with(f))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+static RAWS: SingleThread<EmptyRcCache> = SingleThread(EmptyRcCache::new());
+
+#[cfg(not(feature = "std"))]
+static CSTR: SingleThread<CStrSlot> =
+    SingleThread(CStrSlot(Cell::new(core::ptr::null())));
+
+// The layout of `OsStr` is unspecified on every platform, so it cannot be
+// reinterpreted from the empty byte slice and gets its own allocation.
+#[cfg(feature = "std")]
+thread_local! {
+    static OS_STR: OnceCell<Rc<OsStr>> = const { OnceCell::new() };
+}
+
+// `Path` is a `repr(transparent)` wrapper around `OsStr`, but that is not a
+// documented guarantee, so this cannot reinterpret `OS_STR` either.
+#[cfg(feature = "std")]
+thread_local! {
+    static PATH: OnceCell<Rc<Path>> = const { OnceCell::new() };
+}
+
+impl EmptyRcCache {
+    /// Creates a new, empty cache.
+    pub const fn new() -> Self {
+        Self { raws: [const { Cell::new(core::ptr::null()) }; SLOTS] }
+    }
+
+    /// Calls `f` with the slot for alignment `2^idx`.
+    fn with_slot<R>(
+        &self, idx: AlignIndex, f: impl FnOnce(&Cell<*const ()>) -> R,
+    ) -> R {
+        f(idx.slot(&self.raws))
+    }
+
+    /// Returns a new strong reference to the cached `Rc<[U; 0]>` where U's
+    /// alignment is `2^idx`, as from `Rc::into_raw`.
+    ///
+    /// This and the other by-index methods are not generic, so that they are
+    /// only compiled once no matter how many element types are used.
+    fn acquire_raw(&self, idx: AlignIndex) -> *const () {
+        // Safety: `slot` is the slot for alignment `2^idx`, and nothing else
+        // can run on this thread in the meantime.
+        self.with_slot(idx, |slot| unsafe {
+            shared::acquire::<RcPointer>(slot, idx)
+        })
+    }
+
+    /// Returns a new weak reference to the cached `Rc<[U; 0]>` where U's
+    /// alignment is `2^idx`, as from `Weak::into_raw`.
+    fn acquire_weak_raw(&self, idx: AlignIndex) -> *const () {
+        // Safety: as above.
+        self.with_slot(idx, |slot| unsafe {
+            shared::acquire_weak::<RcPointer>(slot, idx)
+        })
+    }
+
+    fn prewarm_index(&self, idx: AlignIndex) {
+        self.cached_raw(idx);
+    }
+
+    /// Returns the cache's own reference to the cached `Rc<[U; 0]>` where U's
+    /// alignment is `2^idx`, without incrementing it.
+    fn cached_raw(&self, idx: AlignIndex) -> *const () {
+        // Safety: `slot` is the slot for alignment `2^idx`.
+        self.with_slot(idx, |slot| unsafe {
+            shared::prewarm::<RcPointer>(slot, idx)
+        })
+    }
+
+    /// Releases this cache's own reference to each of its allocations, so
+    /// that they are freed once every `Rc` pointing to them has been dropped.
+    /// Later calls will allocate anew.
+    pub fn clear(&self) {
+        // Safety: pointers loaded from `raws` are only used until the method
+        // that loaded them returns, and nothing else runs on this thread
+        // before then.
+        unsafe { shared::clear::<RcPointer>(&self.raws) }
+    }
+
+    fn stats(&self) -> CacheStats {
+        shared::stats(&self.raws)
+    }
+
+    fn is_cached<T>(&self, rc: &Rc<[T]>) -> bool {
+        let Ok(idx) = try_align_index::<T>() else {
+            return false;
+        };
+        let p = idx.slot(&self.raws).get();
+        rc.is_empty() && !p.is_null() && Rc::as_ptr(rc).cast::<()>() == p
+    }
+
+    /// Returns an [`Rc`] which points to an empty array of `T`. This `Rc` may
+    /// or may not share an allocation with other `Rc`s returned from this
+    /// cache, including those pointing to other zero-sized types.
+    pub fn empty_array<T>(&self) -> Rc<[T; 0]> {
+        // An alignment with no slot, which no current compiler allows, gets a
+        // new uncached allocation with `no-panic` rather than panicking.
+        #[cfg(feature = "no-panic")]
+        return self
+            .try_empty_array()
+            .unwrap_or_else(|_| RcPointer::new_empty());
+        #[cfg(not(feature = "no-panic"))]
+        self.try_empty_array().unwrap()
+    }
+
+    fn try_empty_array<T>(&self) -> Result<Rc<[T; 0]>, AlignmentError> {
+        let p = self.acquire_raw(try_align_index::<T>()?);
+        // Safety: `p` is an owned strong reference to an `Rc<[U; 0]>`, and
+        // `[U; 0]` has the same size and alignment as `[T; 0]`.
+        Ok(unsafe { Rc::from_raw(p.cast::<[T; 0]>()) })
+    }
+
+    /// Like `empty_array`, but returns an error instead of aborting if the
+    /// allocation for `T`'s alignment is not cached and cannot be made.
+    #[cfg(all(feature = "allocator_api", not(feature = "no-cache")))]
+    fn try_alloc_empty_array<T>(&self) -> Result<Rc<[T; 0]>, AllocError> {
+        let Ok(idx) = try_align_index::<T>() else {
+            return try_new_empty();
+        };
+        self.with_slot(idx, |slot| {
+            // Safety: `slot` is the slot for `T`'s alignment, and nothing else
+            // can run on this thread before the count is incremented.
+            unsafe {
+                let p = shared::try_get_or_make::<RcPointer, _, _>(
+                    slot,
+                    try_new_empty::<T>,
+                )?;
+                Rc::increment_strong_count(p);
+                Ok(Rc::from_raw(p))
+            }
+        })
+    }
+
+    /// Returns an [`Rc`] which points to an empty slice of `T`. This `Rc` may
+    /// or may not share an allocation with other `Rc`s returned from this
+    /// cache, including those pointing to other zero-sized types.
+    #[inline]
+    pub fn empty_slice<T>(&self) -> Rc<[T]> {
+        self.empty_array()
+    }
+
+    /// Returns an [`Rc`] which points to an empty string slice. This `Rc` may
+    /// or may not share an allocation with other `Rc`s returned from this
+    /// cache, including those pointing to other zero-sized types.
+    pub fn empty_str(&self) -> Rc<str> {
+        str_from_empty_bytes(self.empty_slice())
+    }
+
+    fn empty_weak_array<T>(&self) -> Weak<[T; 0]> {
+        // No `Rc` can be cached for an alignment without a slot.
+        let Ok(idx) = try_align_index::<T>() else {
+            return Weak::new();
+        };
+        let p = self.acquire_weak_raw(idx);
+        // Safety: `p` is an owned weak reference to an `Rc<[U; 0]>`, and
+        // `[U; 0]` has the same size and alignment as `[T; 0]`.
+        unsafe { Weak::from_raw(p.cast::<[T; 0]>()) }
+    }
+}
+
+impl Default for EmptyRcCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for EmptyRcCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Allocates and caches the empty allocation for `align` on the current thread
+/// ahead of time, so that later calls for types with that alignment do not need
+/// to.
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of two, or is larger than any alignment
+/// Rust supports.
+pub fn prewarm_rc_alignment(align: usize) {
+    let idx = shared::alignment_align_index(align);
+    // Nothing needs prewarming once the thread is exiting.
+    let _ = RAWS.try_with(|cache| cache.prewarm_index(idx));
+}
+
+/// Allocates and caches the empty allocation for `T`'s alignment on the
+/// current thread ahead of time, so that later calls for types with that
+/// alignment do not need to.
+#[inline]
+pub fn prewarm_rc<T>() {
+    // There is nothing to prewarm for an alignment without a slot.
+    if let Ok(idx) = try_align_index::<T>() {
+        let _ = RAWS.try_with(|cache| cache.prewarm_index(idx));
+    }
+}
+
+/// Releases this library's own reference to each empty slice, array, and
+/// string slice allocation cached on the current thread, so that they are
+/// freed once every `Rc` pointing to them has been dropped. Later calls will
+/// allocate anew.
+///
+/// The empty C string, OS string, and path allocations are not affected.
+pub fn clear_rc_cache() {
+    // Once the thread is exiting, the cache has already been cleared.
+    let _ = RAWS.try_with(|cache| cache.clear());
+}
+
+/// Returns statistics about the current thread's cache of empty slice, array,
+/// and string slice allocations.
+pub fn rc_cache_stats() -> CacheStats {
+    RAWS.try_with(|cache| cache.stats()).unwrap_or(CacheStats {
+        slots: SLOTS,
+        populated: 0,
+        highest_index: None,
+    })
+}
+
+/// Returns whether `rc` points to the allocation currently cached for `T`'s
+/// alignment on this thread, i.e. whether it was returned by this library on
+/// this thread and the cache has not been cleared since.
+pub fn is_cached_rc<T>(rc: &Rc<[T]>) -> bool {
+    RAWS.try_with(|cache| cache.is_cached(rc)).unwrap_or(false)
 }
 
 /// Returns an [`Rc`] which points to an empty array of `T`. This `Rc` may or
 /// may not share an allocation with other `Rc`s returned from this library on
 /// the same thread, including those pointing to other zero-sized types.
+///
+/// With the `no-cache` feature enabled, or when called from a thread-local's
+/// destructor after the cache has been destroyed, this makes a new allocation.
 pub fn empty_rc_array<T>() -> Rc<[T; 0]> {
-    let idx: usize = core::mem::align_of::<T>()
-        .ilog2()
-        .try_into()
-        .expect("alignment power should fit in usize");
-    RAWS.with_borrow_mut(|raws| {
-        if raws.len() <= idx {
-            raws.resize(idx + 1, std::ptr::null());
+    #[cfg(feature = "no-cache")]
+    return RcPointer::new_empty();
+    #[cfg(not(feature = "no-cache"))]
+    RAWS.try_with(|cache| cache.empty_array())
+        .unwrap_or_else(|_| RcPointer::new_empty())
+}
+
+/// Returns a pointer to this thread's cached empty array of `T`, without
+/// making a new `Rc`, for passing across an FFI boundary.
+///
+/// The pointer is only valid on this thread, since `Rc`'s reference counts are
+/// not atomic. It is kept alive only by the cache's own strong reference, so it
+/// dangles once the cache is cleared by [`clear_rc_cache`] or the thread exits,
+/// unless an `Rc` to the same allocation is still alive. It is never null, and
+/// is aligned for `T`.
+///
+/// This uses the cache even with the `no-cache` feature enabled, since
+/// otherwise nothing would keep the allocation alive. Once the cache has been
+/// destroyed, or for an alignment the cache has no slot for, which no current
+/// compiler allows, this leaks a new allocation instead.
+pub fn empty_rc_ptr<T>() -> NonNull<[T; 0]> {
+    let p = try_align_index::<T>()
+        .ok()
+        .and_then(|idx| RAWS.try_with(|cache| cache.cached_raw(idx)).ok())
+        .unwrap_or_else(|| Rc::into_raw(RcPointer::new_empty::<T>()).cast());
+    // Safety: the cache only holds pointers from `Rc::into_raw`.
+    unsafe { NonNull::new_unchecked(p.cast_mut().cast()) }
+}
+
+/// Returns a new [`Rc`] pointing to the allocation of a pointer returned by
+/// [`empty_rc_ptr`], incrementing its strong count.
+///
+/// # Safety
+///
+/// `ptr` must have been returned by [`empty_rc_ptr::<U>`](empty_rc_ptr) on
+/// this thread, for some `U` with the same alignment as `T`, and its
+/// allocation must still be alive: the cache must not have been cleared since,
+/// or some `Rc` to the allocation must still exist.
+pub unsafe fn empty_rc_from_ptr<T>(ptr: NonNull<[T; 0]>) -> Rc<[T; 0]> {
+    let ptr = ptr.as_ptr().cast_const();
+    // Safety: `ptr` is from `Rc::into_raw` on an `Rc<[U; 0]>`, which has the
+    // same layout as `Rc<[T; 0]>`, and its allocation is alive on this thread.
+    unsafe {
+        Rc::increment_strong_count(ptr);
+        Rc::from_raw(ptr)
+    }
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_rc_array`] would return on this thread. Only the weak count of the
+/// allocation is incremented.
+///
+/// The cache's strong reference is released by [`clear_rc_cache`] and when the
+/// thread exits, so upgrading the `Weak` can fail after either, including from
+/// another thread-local's destructor. Once the cache has been destroyed, this
+/// returns a `Weak` which never upgrades.
+///
+/// This uses the cache even with the `no-cache` feature enabled, since
+/// otherwise nothing would keep the allocation alive.
+pub fn empty_rc_weak_array<T>() -> Weak<[T; 0]> {
+    RAWS.try_with(|cache| cache.empty_weak_array())
+        .unwrap_or_else(|_| Weak::new())
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_rc_slice`] would return on this thread. Only the weak count of the
+/// allocation is incremented.
+///
+/// See [`empty_rc_weak_array`] for how this interacts with thread exit.
+#[inline]
+pub fn empty_rc_weak<T>() -> Weak<[T]> {
+    empty_rc_weak_array()
+}
+
+/// An unsized type which this library can make empty [`Rc`]s of.
+///
+/// This is implemented for `[T]`, `str`, `CStr`, and, with the relevant
+/// features, `OsStr`, `Path`, and `BStr`. It cannot be implemented outside
+/// this library.
+pub trait EmptyRcTarget: Sealed {
+    /// Returns an [`Rc`] which points to an empty `Self`, like the
+    /// `empty_rc_*` function for `Self`.
+    fn empty_rc() -> Rc<Self>;
+}
+
+impl<T> EmptyRcTarget for [T] {
+    #[inline]
+    fn empty_rc() -> Rc<[T]> {
+        empty_rc_array()
+    }
+}
+
+impl EmptyRcTarget for str {
+    fn empty_rc() -> Rc<str> {
+        str_from_empty_bytes(empty_rc())
+    }
+}
+
+/// Returns this thread's empty C string allocation, kept alive by `CSTR`'s
+/// own reference.
+fn cached_cstr(slot: &CStrSlot) -> *const [u8; 1] {
+    // Safety: `CSTR` only ever holds an `Rc<[u8; 1]>` holding a NUL byte.
+    unsafe { shared::get_or_make::<RcPointer, _>(&slot.0, || Rc::new([0u8])) }
+}
+
+impl EmptyRcTarget for CStr {
+    fn empty_rc() -> Rc<CStr> {
+        #[cfg(feature = "no-cache")]
+        return Rc::from(c"");
+        #[cfg(not(feature = "no-cache"))]
+        {
+            let bytes: Rc<[u8]> = match CSTR.try_with(|slot| unsafe {
+                let p = cached_cstr(slot);
+                Rc::increment_strong_count(p);
+                Rc::from_raw(p)
+            }) {
+                Ok(bytes) => bytes,
+                Err(_) => return Rc::from(c""),
+            };
+            debug_assert!(CStr::from_bytes_with_nul(&bytes).is_ok());
+            // This is the same conversion `alloc` uses for
+            // `From<&CStr> for Rc<CStr>`.
+            unsafe { Rc::from_raw(Rc::into_raw(bytes) as *const CStr) }
         }
-        let ptr = &mut raws[idx];
-        unsafe { get_or_make_rc::<T>(ptr) }
-    })
+    }
+}
+
+#[cfg(feature = "std")]
+impl EmptyRcTarget for OsStr {
+    fn empty_rc() -> Rc<OsStr> {
+        #[cfg(feature = "no-cache")]
+        return Rc::from(OsStr::new(""));
+        #[cfg(not(feature = "no-cache"))]
+        OS_STR.with(|os_str| {
+            os_str.get_or_init(|| Rc::from(OsStr::new(""))).clone()
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl EmptyRcTarget for Path {
+    fn empty_rc() -> Rc<Path> {
+        #[cfg(feature = "no-cache")]
+        return Rc::from(Path::new(""));
+        #[cfg(not(feature = "no-cache"))]
+        PATH.with(|path| path.get_or_init(|| Rc::from(Path::new(""))).clone())
+    }
+}
+
+#[cfg(feature = "bstr")]
+impl EmptyRcTarget for BStr {
+    fn empty_rc() -> Rc<BStr> {
+        let rc: Rc<[u8]> = empty_rc();
+        // `BStr` is documented to have the same representation as `[u8]`.
+        unsafe { Rc::from_raw(Rc::into_raw(rc) as *const BStr) }
+    }
+}
+
+/// Returns an [`Rc`] which points to an empty `U`, for use in code which is
+/// generic over the kind of empty value. This is the same as calling the
+/// `empty_rc_*` function for `U`.
+#[inline]
+pub fn empty_rc<U: ?Sized + EmptyRcTarget>() -> Rc<U> {
+    U::empty_rc()
 }
 
 /// Returns an [`Rc`] which points to an empty slice of `T`. This `Rc` may or
@@ -49,18 +582,363 @@ pub fn empty_rc_array<T>() -> Rc<[T; 0]> {
 /// the same thread, including those pointing to other zero-sized types.
 #[inline]
 pub fn empty_rc_slice<T>() -> Rc<[T]> {
-    empty_rc_array()
+    empty_rc()
+}
+
+/// Returns an [`Rc`] which points to a new, uncached empty slice of `T`, so
+/// that it is the only reference to its allocation.
+///
+/// The `Rc`s returned by [`empty_rc_slice`] are shared with the cache, so
+/// [`Rc::get_mut`] always fails on them and [`Rc::make_mut`] always clones.
+/// This one is unique until it is cloned, but it costs an allocation on every
+/// call, which the shared empties avoid.
+pub fn unique_empty_rc_slice<T>() -> Rc<[T]> {
+    RcPointer::new_empty::<T>()
+}
+
+/// Like [`empty_rc_slice`], but returns an error instead of aborting if the
+/// allocation for `T`'s alignment is not cached on this thread yet and cannot
+/// be made.
+///
+/// Once the allocation for `T`'s alignment is cached, this never fails. With
+/// the `no-cache` feature enabled, or once the thread is exiting, it makes a
+/// new allocation every time, which can. This needs the `allocator_api`
+/// feature, since there is no stable way to allocate an `Rc` fallibly.
+#[cfg(feature = "allocator_api")]
+pub fn try_empty_rc_slice<T>() -> Result<Rc<[T]>, AllocError> {
+    #[cfg(feature = "no-cache")]
+    return Ok(try_new_empty::<T>()?);
+    #[cfg(not(feature = "no-cache"))]
+    {
+        // Nothing is cached once the thread is exiting.
+        let rc = RAWS
+            .try_with(|cache| cache.try_alloc_empty_array::<T>())
+            .unwrap_or_else(|_| try_new_empty())?;
+        Ok(rc)
+    }
+}
+
+/// Returns an [`Rc`] which points to an empty slice of `T`, allocated with
+/// `alloc`.
+///
+/// Unlike [`empty_rc_slice`], this makes a new allocation every time, since an
+/// allocation cannot be shared between different allocators.
+#[cfg(feature = "allocator_api")]
+pub fn empty_rc_slice_in<T, A: Allocator>(alloc: A) -> Rc<[T], A> {
+    // As in `RcPointer::new_empty`, `Rc::new_in([], alloc)` would build the
+    // whole allocation on the stack first.
+    let rc = Rc::<[T; 0], A>::new_uninit_in(alloc);
+    // Safety: `[T; 0]` has no bytes to initialize.
+    unsafe { rc.assume_init() }
+}
+
+/// Use this instead of `Rc::<[T]>::default()` for an empty slice which shares
+/// an allocation with every other one of its alignment on this thread.
+///
+/// This is the same as [`empty_rc_slice`]. `Rc::<[T]>::default()` may
+/// allocate every time, at least for larger alignments.
+///
+/// ```
+/// use empty_rc_slice::default_empty_rc_slice;
+/// use std::rc::Rc;
+///
+/// #[repr(align(64))]
+/// struct Align64;
+///
+/// let a: Rc<[Align64]> = Rc::default();
+/// let b: Rc<[Align64]> = Rc::default();
+/// assert!(!Rc::ptr_eq(&a, &b));
+///
+/// let a = default_empty_rc_slice::<Align64>();
+/// let b = default_empty_rc_slice::<Align64>();
+/// # #[cfg(not(feature = "no-cache"))]
+/// assert!(Rc::ptr_eq(&a, &b));
+/// ```
+#[inline]
+pub fn default_empty_rc_slice<T>() -> Rc<[T]> {
+    empty_rc_slice()
+}
+
+/// Returns an [`Rc`] which points to an empty slice of `MaybeUninit<T>`. Since
+/// `MaybeUninit<T>` has the same alignment as `T`, this shares an allocation
+/// with [`empty_rc_slice::<T>`](empty_rc_slice) on the same thread.
+#[inline]
+pub fn empty_rc_uninit_slice<T>() -> Rc<[MaybeUninit<T>]> {
+    empty_rc_slice()
 }
 
 /// Returns an [`Rc`] which points to an empty string slice. This `Rc` may or
 /// may not share an allocation with other `Rc`s returned from this library on
 /// the same thread, including those pointing to other zero-sized types.
+#[inline]
 pub fn empty_rc_str() -> Rc<str> {
-    let rc: Rc<[u8]> = empty_rc_slice();
-    debug_assert!(core::str::from_utf8(&rc).is_ok());
+    empty_rc()
+}
+
+fn str_from_empty_bytes(rc: Rc<[u8]>) -> Rc<str> {
+    debug_assert!(rc.is_empty());
+    // Safety: `rc` is empty, so it is valid UTF-8, and `str` has the same
+    // layout as `[u8]`.
     unsafe { Rc::from_raw(Rc::into_raw(rc) as *const str) }
 }
 
+/// Returns an [`Rc`] which points to an empty C string, i.e. a single NUL
+/// byte. This `Rc` may or may not share an allocation with other `Rc`s
+/// returned from this library on the same thread.
+#[inline]
+pub fn empty_rc_cstr() -> Rc<CStr> {
+    empty_rc()
+}
+
+/// Returns an [`Rc`] which points to an empty OS string slice. This `Rc` may or
+/// may not share an allocation with other `Rc`s returned from this library on
+/// the same thread.
+#[cfg(feature = "std")]
+#[inline]
+pub fn empty_rc_os_str() -> Rc<OsStr> {
+    empty_rc()
+}
+
+/// Returns an [`Rc`] which points to an empty path. This `Rc` may or may not
+/// share an allocation with other `Rc`s returned from this library on the same
+/// thread.
+#[cfg(feature = "std")]
+#[inline]
+pub fn empty_rc_path() -> Rc<Path> {
+    empty_rc()
+}
+
+/// Returns an [`Rc`] which points to an empty byte string. This shares an
+/// allocation with [`empty_rc_slice::<u8>`](empty_rc_slice).
+#[cfg(feature = "bstr")]
+#[inline]
+pub fn empty_rc_bstr() -> Rc<BStr> {
+    empty_rc()
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_rc_str`] would return on this thread. Only the weak count of the
+/// allocation is incremented.
+pub fn empty_rc_weak_str() -> Weak<str> {
+    let weak: Weak<[u8]> = empty_rc_weak();
+    // Safety: the allocation is empty, so it is valid UTF-8, and `str` has the
+    // same layout as `[u8]`.
+    unsafe { Weak::from_raw(Weak::into_raw(weak) as *const str) }
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_rc_cstr`] would return on this thread. Only the weak count of the
+/// allocation is incremented. The allocation is released when the thread
+/// exits, so upgrading the `Weak` can fail from another thread-local's
+/// destructor. Once it has been released, this returns a `Weak` which never
+/// upgrades.
+pub fn empty_rc_weak_cstr() -> Weak<CStr> {
+    // Safety: the allocation is kept alive by `CSTR`'s own reference.
+    let bytes: Weak<[u8; 1]> = match CSTR.try_with(|slot| unsafe {
+        Weak::from_raw(RcPointer::downgrade_raw(cached_cstr(slot)))
+    }) {
+        Ok(bytes) => bytes,
+        Err(_) => return Rc::downgrade(&Rc::from(c"")),
+    };
+    let bytes: Weak<[u8]> = bytes;
+    // As in `empty_rc_cstr`.
+    unsafe { Weak::from_raw(Weak::into_raw(bytes) as *const CStr) }
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_rc_os_str`] would return on this thread. Only the weak count of the
+/// allocation is incremented. The allocation is released when the thread
+/// exits, so upgrading the `Weak` can fail from another thread-local's
+/// destructor.
+#[cfg(feature = "std")]
+pub fn empty_rc_weak_os_str() -> Weak<OsStr> {
+    OS_STR.with(|os_str| {
+        Rc::downgrade(os_str.get_or_init(|| Rc::from(OsStr::new(""))))
+    })
+}
+
+/// Returns a [`Weak`] which points to the same allocation as
+/// [`empty_rc_path`] would return on this thread. Only the weak count of the
+/// allocation is incremented. The allocation is released when the thread
+/// exits, so upgrading the `Weak` can fail from another thread-local's
+/// destructor.
+#[cfg(feature = "std")]
+pub fn empty_rc_weak_path() -> Weak<Path> {
+    PATH.with(|path| {
+        Rc::downgrade(path.get_or_init(|| Rc::from(Path::new(""))))
+    })
+}
+
+/// An `Rc<[T]>` which is known to be empty, for APIs which want to state that
+/// in their signatures.
+///
+/// This can only be made by this library, from [`empty_rc_slice`].
+#[derive(Clone)]
+pub struct EmptyRc<T> {
+    rc: Rc<[T]>,
+}
+
+impl<T> EmptyRc<T> {
+    /// Returns an empty slice from [`empty_rc_slice`].
+    #[inline]
+    pub fn new() -> Self {
+        Self { rc: empty_rc_slice() }
+    }
+
+    /// Returns the underlying `Rc`.
+    #[inline]
+    pub fn into_rc(self) -> Rc<[T]> {
+        self.rc
+    }
+}
+
+impl<T> Default for EmptyRc<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> core::ops::Deref for EmptyRc<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        &self.rc
+    }
+}
+
+impl<T> From<EmptyRc<T>> for Rc<[T]> {
+    #[inline]
+    fn from(empty: EmptyRc<T>) -> Self {
+        empty.into_rc()
+    }
+}
+
+impl<T> core::fmt::Debug for EmptyRc<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("EmptyRc")
+    }
+}
+
+/// Associated functions for making empty [`Rc`]s, so that they can be named
+/// through the type being made instead of imported separately.
+///
+/// This is implemented for `Rc<[T]>`, `Rc<[T; 0]>`, and `Rc<str>`, and cannot
+/// be implemented outside this library.
+///
+/// ```
+/// use empty_rc_slice::RcExt;
+/// use std::rc::Rc;
+///
+/// let a = <Rc<[u8]>>::empty_slice();
+/// let b = <Rc<[u8]>>::empty_array();
+/// assert!(a.is_empty() && b.is_empty());
+/// assert_eq!(&*<Rc<str>>::empty_str(), "");
+/// ```
+pub trait RcExt: Sealed {
+    /// The element type of the empty slices this makes.
+    type Elem;
+
+    /// Like [`empty_rc_slice`].
+    #[inline]
+    fn empty_slice() -> Rc<[Self::Elem]> {
+        empty_rc_slice()
+    }
+
+    /// Like [`empty_rc_array`].
+    #[inline]
+    fn empty_array() -> Rc<[Self::Elem; 0]> {
+        empty_rc_array()
+    }
+
+    /// Like [`empty_rc_str`]. This is only available on `Rc<str>`.
+    #[inline]
+    fn empty_str() -> Rc<str>
+    where
+        Self: Borrow<str>,
+    {
+        empty_rc_str()
+    }
+}
+
+impl<T> Sealed for Rc<[T]> {}
+impl<T> RcExt for Rc<[T]> {
+    type Elem = T;
+}
+
+impl<T> Sealed for Rc<[T; 0]> {}
+impl<T> RcExt for Rc<[T; 0]> {
+    type Elem = T;
+}
+
+impl Sealed for Rc<str> {}
+impl RcExt for Rc<str> {
+    type Elem = u8;
+}
+
+/// Declares a `static` holding an empty `Rc<[T]>` per thread for one type, so
+/// that later uses on each thread only clone it instead of going through the
+/// cache.
+///
+/// `empty_rc_static!(NAME: [T])` declares `static NAME`, whose `get` method
+/// returns an `Rc<[T]>`. The first call on each thread gets it from
+/// [`empty_rc_slice`], so it shares an allocation with that thread's cache.
+///
+/// ```
+/// use empty_rc_slice::empty_rc_static;
+/// use std::rc::Rc;
+///
+/// empty_rc_static!(EMPTY_NAMES: [String]);
+///
+/// let names: Rc<[String]> = EMPTY_NAMES.get();
+/// assert!(names.is_empty());
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! empty_rc_static {
+    ($(#[$attr:meta])* $vis:vis $name:ident: [$t:ty]) => {
+        $(#[$attr])*
+        $vis static $name: $crate::__private::EmptyRcStatic<$t> = {
+            $crate::__private::thread_local! {
+                static RC: $crate::__private::EmptyRcStaticCell<$t> =
+                    const { $crate::__private::EmptyRcStaticCell::new() };
+            }
+            $crate::__private::EmptyRcStatic::new(&RC)
+        };
+    };
+}
+
+/// The type of the statics declared by [`empty_rc_static!`].
+#[cfg(feature = "std")]
+pub struct EmptyRcStatic<T: 'static> {
+    rc: &'static std::thread::LocalKey<EmptyRcStaticCell<T>>,
+}
+
+/// The thread-local behind each [`EmptyRcStatic`].
+#[cfg(feature = "std")]
+pub type EmptyRcStaticCell<T> = OnceCell<Rc<[T]>>;
+
+#[cfg(feature = "std")]
+impl<T> EmptyRcStatic<T> {
+    #[doc(hidden)]
+    pub const fn new(
+        rc: &'static std::thread::LocalKey<EmptyRcStaticCell<T>>,
+    ) -> Self {
+        Self { rc }
+    }
+
+    /// Returns a new reference to this thread's empty slice for this static.
+    #[inline]
+    pub fn get(&self) -> Rc<[T]> {
+        self.rc
+            .try_with(|rc| rc.get_or_init(empty_rc_slice).clone())
+            // Once the thread is exiting, fall back to the cache.
+            .unwrap_or_else(|_| empty_rc_slice())
+    }
+}
+
+#[cfg(not(feature = "no-cache"))]
 #[test]
 fn works() {
     extern crate std;
@@ -74,9 +952,328 @@ fn works() {
         let b: Rc<[u64; 0]> = empty_rc_array();
         assert!(std::ptr::eq(&a[..], &b[..]));
         let s: Rc<str> = empty_rc_str();
-        Rc::into_raw(s) as *const () as usize
+        Rc::into_raw(s).addr()
     });
     let _: Rc<[u32]> = empty_rc_slice();
     let empty_str = empty_str.join().unwrap();
-    assert_ne!(u8.as_ptr() as usize, empty_str);
+    assert_ne!(u8.as_ptr().addr(), empty_str);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn weak_upgrades_to_shared() {
+    let weak: Weak<[u32]> = empty_rc_weak();
+    let a: Rc<[u32]> = weak.upgrade().unwrap();
+    let b: Rc<[u32]> = empty_rc_slice();
+    assert!(Rc::ptr_eq(&a, &b));
+    let weak: Weak<[u32; 0]> = empty_rc_weak_array();
+    assert!(Rc::ptr_eq(&weak.upgrade().unwrap(), &empty_rc_array()));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn cstr() {
+    let a: Rc<CStr> = empty_rc_cstr();
+    assert!(a.to_bytes().is_empty());
+    assert_eq!(a.to_bytes_with_nul(), [0]);
+    let b: Rc<CStr> = empty_rc_cstr();
+    assert!(Rc::ptr_eq(&a, &b));
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn os_str() {
+    let a: Rc<OsStr> = empty_rc_os_str();
+    assert!(a.is_empty());
+    let b: Rc<OsStr> = empty_rc_os_str();
+    assert!(Rc::ptr_eq(&a, &b));
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn path() {
+    let a: Rc<Path> = empty_rc_path();
+    assert!(a.as_os_str().is_empty());
+    assert!(a.components().next().is_none());
+    let b: Rc<Path> = empty_rc_path();
+    assert!(Rc::ptr_eq(&a, &b));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn uninit_shares_with_init() {
+    let a: Rc<[MaybeUninit<u64>]> = empty_rc_uninit_slice();
+    let b: Rc<[u64]> = empty_rc_slice();
+    assert!(core::ptr::addr_eq(Rc::as_ptr(&a), Rc::as_ptr(&b)));
+}
+
+#[cfg(all(feature = "bstr", not(feature = "no-cache")))]
+#[test]
+fn bstr() {
+    let s: Rc<BStr> = empty_rc_bstr();
+    assert!(s.is_empty());
+    let b: Rc<[u8]> = empty_rc_slice();
+    assert!(core::ptr::addr_eq(Rc::as_ptr(&s), Rc::as_ptr(&b)));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn weak_str_upgrades_to_shared() {
+    let weak: Weak<str> = empty_rc_weak_str();
+    let a: Rc<str> = weak.upgrade().unwrap();
+    assert!(Rc::ptr_eq(&a, &empty_rc_str()));
+}
+
+#[cfg(all(feature = "no-panic", not(feature = "no-cache")))]
+#[test]
+fn no_panic_still_caches() {
+    let a: Rc<[u64; 0]> = empty_rc_array();
+    assert!(Rc::ptr_eq(&a, &empty_rc_array()));
+    let cache = EmptyRcCache::new();
+    assert!(Rc::ptr_eq(&cache.empty_array::<u64>(), &cache.empty_array()));
+}
+
+#[test]
+fn unique_is_unique() {
+    let mut a: Rc<[u8]> = unique_empty_rc_slice();
+    assert!(a.is_empty());
+    assert_eq!(Rc::strong_count(&a), 1);
+    assert!(Rc::get_mut(&mut a).is_some());
+    assert!(!is_cached_rc(&a));
+    #[cfg(not(feature = "no-cache"))]
+    {
+        let mut b: Rc<[u8]> = empty_rc_slice();
+        assert!(Rc::get_mut(&mut b).is_none());
+    }
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn weak_cstr_upgrades_to_shared() {
+    let weak: Weak<CStr> = empty_rc_weak_cstr();
+    let a: Rc<CStr> = weak.upgrade().unwrap();
+    assert!(Rc::ptr_eq(&a, &empty_rc_cstr()));
+    assert_eq!(a.to_bytes_with_nul(), [0]);
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn weak_os_str_and_path_upgrade_to_shared() {
+    let weak: Weak<OsStr> = empty_rc_weak_os_str();
+    assert!(Rc::ptr_eq(&weak.upgrade().unwrap(), &empty_rc_os_str()));
+    let weak: Weak<Path> = empty_rc_weak_path();
+    assert!(Rc::ptr_eq(&weak.upgrade().unwrap(), &empty_rc_path()));
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn stats() {
+    std::thread::spawn(|| {
+        let _a: Rc<[u8]> = empty_rc_slice();
+        let _b: Rc<[u64]> = empty_rc_slice();
+        let stats = rc_cache_stats();
+        assert_eq!(stats.slots, 30);
+        assert_eq!(stats.populated, 2);
+        assert_eq!(stats.highest_index, Some(3));
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn prewarm() {
+    std::thread::spawn(|| {
+        prewarm_rc_alignment(8);
+        prewarm_rc::<u64>();
+        let before = rc_cache_stats();
+        assert_eq!(before.slots, 30);
+        assert_eq!(before.populated, 1);
+        let _a: Rc<[u64]> = empty_rc_slice();
+        assert_eq!(rc_cache_stats(), before);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+#[should_panic = "alignment must be a power of two"]
+fn prewarm_non_power_of_two() {
+    prewarm_rc_alignment(12);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn is_cached() {
+    use alloc::vec;
+
+    assert!(is_cached_rc::<u16>(&empty_rc_slice()));
+    assert!(!is_cached_rc::<u16>(&Rc::from(vec![])));
+    assert!(!is_cached_rc::<u16>(&Rc::from(vec![1, 2, 3])));
+    let other_thread = std::thread::spawn(|| {
+        let rc: Rc<[u16]> = empty_rc_slice();
+        is_cached_rc(&rc)
+    });
+    assert!(other_thread.join().unwrap());
+}
+
+#[test]
+fn separate_caches() {
+    let a = EmptyRcCache::new();
+    let b = EmptyRcCache::default();
+    let x: Rc<[u32]> = a.empty_slice();
+    let y: Rc<[u32]> = b.empty_slice();
+    assert!(!Rc::ptr_eq(&x, &y));
+    assert!(Rc::ptr_eq(&x, &a.empty_slice()));
+    let z: Rc<[u32; 0]> = a.empty_array();
+    assert!(core::ptr::addr_eq(Rc::as_ptr(&x), Rc::as_ptr(&z)));
+    assert!(!Rc::ptr_eq(&a.empty_str(), &b.empty_str()));
+    a.clear();
+    assert!(!Rc::ptr_eq(&x, &a.empty_slice()));
+    drop((a, b));
+    assert!(x.is_empty());
+}
+
+#[cfg(feature = "no-cache")]
+#[test]
+fn no_cache() {
+    let a: Rc<[u32]> = empty_rc_slice();
+    let b: Rc<[u32]> = empty_rc_slice();
+    assert!(!Rc::ptr_eq(&a, &b));
+    assert_eq!(Rc::strong_count(&a), 1);
+    assert!(!Rc::ptr_eq(&empty_rc_str(), &empty_rc_str()));
+
+    let c = empty_rc_cstr();
+    assert!(c.is_empty());
+    assert!(!Rc::ptr_eq(&c, &empty_rc_cstr()));
+    assert_eq!(Rc::strong_count(&c), 1);
+    #[cfg(feature = "std")]
+    {
+        let o = empty_rc_os_str();
+        assert!(o.is_empty());
+        assert!(!Rc::ptr_eq(&o, &empty_rc_os_str()));
+        let p = empty_rc_path();
+        assert!(p.as_os_str().is_empty());
+        assert!(!Rc::ptr_eq(&p, &empty_rc_path()));
+    }
+}
+
+#[test]
+fn huge_alignment() {
+    #[repr(align(1048576))]
+    struct Align1M;
+
+    std::thread::spawn(|| {
+        let a: Rc<[Align1M]> = empty_rc_slice();
+        assert!(Rc::as_ptr(&a).cast::<Align1M>().is_aligned());
+        // The cache's own size does not depend on the alignments used.
+        assert_eq!(rc_cache_stats().slots, MAX_ALIGN_INDEX + 1);
+    })
+    .join()
+    .unwrap();
+}
+
+#[cfg(all(feature = "allocator_api", not(feature = "no-cache")))]
+#[test]
+fn try_slice() {
+    std::thread::spawn(|| {
+        let a: Rc<[u64]> = try_empty_rc_slice().unwrap();
+        assert!(is_cached_rc(&a));
+        assert!(Rc::ptr_eq(&a, &try_empty_rc_slice().unwrap()));
+    })
+    .join()
+    .unwrap();
+}
+
+#[cfg(feature = "allocator_api")]
+#[test]
+fn slice_in() {
+    use alloc::alloc::{AllocError, Global};
+    use core::{alloc::Layout, ptr::NonNull};
+
+    struct Counting<'a>(&'a Cell<usize>);
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(
+            &self, layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.set(self.0.get() - 1);
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[repr(align(1048576))]
+    struct Align1M;
+
+    let live = Cell::new(0);
+    let a: Rc<[u64], _> = empty_rc_slice_in(Counting(&live));
+    assert!(a.is_empty());
+    assert_eq!(live.get(), 1);
+    drop(a);
+    assert_eq!(live.get(), 0);
+
+    // Too large to build on the stack first.
+    let a: Rc<[Align1M], _> = empty_rc_slice_in(Counting(&live));
+    assert!(Rc::as_ptr(&a).cast::<Align1M>().is_aligned());
+    drop(a);
+    assert_eq!(live.get(), 0);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn generic() {
+    let a: Rc<[u32]> = empty_rc::<[u32]>();
+    assert!(Rc::ptr_eq(&a, &empty_rc_slice()));
+    let s: Rc<str> = empty_rc::<str>();
+    assert!(Rc::ptr_eq(&s, &empty_rc_str()));
+    assert!(empty_rc::<CStr>().is_empty());
+}
+
+#[cfg(all(feature = "std", not(feature = "no-cache")))]
+#[test]
+fn static_macro() {
+    #[repr(align(512))]
+    struct Align512;
+
+    crate::empty_rc_static!(EMPTY: [Align512]);
+
+    let a = EMPTY.get();
+    assert!(Rc::ptr_eq(&a, &EMPTY.get()));
+    assert!(Rc::ptr_eq(&a, &empty_rc_slice()));
+    std::thread::spawn(|| {
+        let b = EMPTY.get();
+        assert!(Rc::ptr_eq(&b, &empty_rc_slice()));
+    })
+    .join()
+    .unwrap();
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn ptr_round_trip() {
+    #[repr(align(2048))]
+    struct Align2048;
+
+    let p = empty_rc_ptr::<Align2048>();
+    assert!(p.is_aligned());
+    let a = unsafe { empty_rc_from_ptr(p) };
+    assert!(Rc::ptr_eq(&a, &empty_rc_array()));
+    assert_eq!(Rc::as_ptr(&a), p.as_ptr().cast_const());
+    // The cache's own reference and `a`.
+    assert_eq!(Rc::strong_count(&a), 2);
+    drop(a);
+    assert_eq!(empty_rc_ptr::<Align2048>(), p);
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn empty_rc_newtype() {
+    let e = EmptyRc::<u8>::new();
+    assert!(e.is_empty());
+    let a = e.clone().into_rc();
+    assert!(is_cached_rc(&a));
+    assert!(Rc::ptr_eq(&a, &Rc::from(e)));
 }
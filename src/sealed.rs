@@ -0,0 +1,16 @@
+#[cfg(feature = "std")]
+extern crate std;
+
+/// Keeps this library's public traits from being implemented elsewhere, so
+/// that they can gain methods without breaking anyone.
+pub trait Sealed {}
+
+impl<T> Sealed for [T] {}
+impl Sealed for str {}
+impl Sealed for core::ffi::CStr {}
+#[cfg(feature = "std")]
+impl Sealed for std::ffi::OsStr {}
+#[cfg(feature = "std")]
+impl Sealed for std::path::Path {}
+#[cfg(feature = "bstr")]
+impl Sealed for bstr::BStr {}
@@ -0,0 +1,74 @@
+//! Deserializing empty sequences into the cached empty `Arc<[T]>`.
+
+use crate::empty_arc_slice;
+use alloc::{sync::Arc, vec::Vec};
+use core::{fmt, marker::PhantomData};
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+/// Deserializes a sequence into an [`Arc`], returning the cached empty slice
+/// from [`empty_arc_slice`] if the sequence is empty.
+///
+/// `Arc<[T]>`'s own `Deserialize` implementation allocates anew even for an
+/// empty sequence. This is meant for `#[serde(deserialize_with = ...)]`:
+///
+/// ```
+/// # #[cfg(not(feature = "no-cache"))]
+/// # {
+/// use std::sync::Arc;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Row {
+///     #[serde(
+///         deserialize_with = "empty_rc_slice::deserialize_empty_arc_slice"
+///     )]
+///     tags: Arc<[u32]>,
+/// }
+///
+/// let row: Row = serde_json::from_str(r#"{ "tags": [] }"#).unwrap();
+/// assert!(empty_rc_slice::is_cached_arc(&row.tags));
+/// # }
+/// ```
+pub fn deserialize_empty_arc_slice<'de, D, T>(
+    deserializer: D,
+) -> Result<Arc<[T]>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    const MAX_PREALLOCATION: usize = 1024 * 1024;
+
+    struct SeqVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for SeqVisitor<T> {
+        type Value = Arc<[T]>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a sequence")
+        }
+
+        fn visit_seq<S: SeqAccess<'de>>(
+            self, mut seq: S,
+        ) -> Result<Arc<[T]>, S::Error> {
+            // Checking for a first element before allocating the `Vec` means
+            // empty sequences do not allocate at all.
+            let Some(first) = seq.next_element()? else {
+                return Ok(empty_arc_slice());
+            };
+            // The size hint comes from the input, so like serde's own
+            // `Vec<T>` impl, do not trust it for more than a megabyte.
+            let max = MAX_PREALLOCATION / core::mem::size_of::<T>().max(1);
+            let hint = seq.size_hint().unwrap_or(0).min(max);
+            let mut elements = Vec::with_capacity(hint.saturating_add(1));
+            elements.push(first);
+            while let Some(element) = seq.next_element()? {
+                elements.push(element);
+            }
+            Ok(Arc::from(elements))
+        }
+    }
+
+    deserializer.deserialize_seq(SeqVisitor(PhantomData))
+}
@@ -0,0 +1,244 @@
+//! The parts of the `Arc` and `Rc` caches which do not depend on which
+//! reference-counted pointer, or which kind of slot, they use.
+
+use crate::{
+    align::{with_align_index, AlignIndex, WithAlign, MAX_ALIGN_INDEX},
+    AlignmentError, CacheStats,
+};
+use core::{convert::Infallible, marker::PhantomData};
+
+/// A reference-counted pointer type whose allocations the caches can hold.
+///
+/// This is implemented for marker types rather than the pointers themselves,
+/// since it is the family of pointers (`Arc<T>` for every `T`) that matters.
+pub(crate) trait SharedPointer {
+    type Strong<T: ?Sized>;
+
+    /// Allocates a new empty array.
+    fn new_empty<A>() -> Self::Strong<[A; 0]>;
+
+    fn into_raw<T: ?Sized>(this: Self::Strong<T>) -> *const T;
+
+    /// Safety: as for `Arc::from_raw`.
+    unsafe fn from_raw<T>(ptr: *const T) -> Self::Strong<T>;
+
+    /// Safety: as for `Arc::increment_strong_count`.
+    unsafe fn increment_strong<T>(ptr: *const T);
+
+    /// Safety: as for `Arc::decrement_strong_count`.
+    unsafe fn decrement_strong<T>(ptr: *const T);
+}
+
+/// A reference-counted pointer type which also has weak references.
+pub(crate) trait WeakPointer: SharedPointer {
+    /// Returns a new weak reference to the allocation of the strong reference
+    /// `ptr`, as from `Weak::into_raw`.
+    ///
+    /// Safety: `ptr` must point to a live allocation, as from `into_raw`.
+    unsafe fn downgrade_raw<T>(ptr: *const T) -> *const T;
+}
+
+/// Where a cache stores either null or one `into_raw`'d allocation.
+pub(crate) trait Slot {
+    fn load(&self) -> *const ();
+
+    /// Stores `new` if the slot is null, and otherwise returns what it holds.
+    fn publish(&self, new: *const ()) -> Result<(), *const ()>;
+
+    /// Replaces the contents of the slot with null, returning what it held.
+    fn take(&self) -> *const ();
+}
+
+/// Safety: `slot` must only ever be initialized with an `into_raw`'d pointer
+/// to a `U` with the same layout and value as the result of `make`.
+///
+/// The returned pointer is kept alive by the cache's own strong reference.
+pub(crate) unsafe fn get_or_make<P: SharedPointer, A>(
+    slot: &impl Slot, make: impl FnOnce() -> P::Strong<A>,
+) -> *const A {
+    // Safety: as for this function.
+    let p = unsafe { try_get_or_make::<P, _, Infallible>(slot, || Ok(make())) };
+    match p {
+        Ok(p) => p,
+        Err(e) => match e {},
+    }
+}
+
+/// Like [`get_or_make`], but `make` may fail, in which case the slot is left
+/// empty and its error is returned.
+///
+/// Safety: as for [`get_or_make`].
+pub(crate) unsafe fn try_get_or_make<P: SharedPointer, A, E>(
+    slot: &impl Slot, make: impl FnOnce() -> Result<P::Strong<A>, E>,
+) -> Result<*const A, E> {
+    let p = slot.load().cast::<A>();
+    if !p.is_null() {
+        // A misaligned pointer here would mean the slot was filled for a
+        // different alignment.
+        debug_assert!(p.is_aligned());
+        return Ok(p);
+    }
+    let raw = P::into_raw(make()?);
+    match slot.publish(raw.cast()) {
+        Ok(()) => Ok(raw),
+        Err(p) => {
+            unsafe {
+                drop(P::from_raw(raw));
+            }
+            let p = p.cast::<A>();
+            debug_assert!(p.is_aligned());
+            Ok(p)
+        }
+    }
+}
+
+/// The index of `T`'s alignment in a cache, or an error if the cache has no
+/// slot for it.
+///
+/// Nothing here can panic: alignments are powers of two, so the base-2
+/// logarithm is just the number of trailing zeros.
+#[inline]
+pub(crate) fn try_align_index<T>() -> Result<AlignIndex, AlignmentError> {
+    let idx = core::mem::align_of::<T>().trailing_zeros();
+    usize::try_from(idx).ok().and_then(AlignIndex::new).ok_or(AlignmentError)
+}
+
+/// Like [`alignment_index`], but returns the crate's own index type.
+pub(crate) fn alignment_align_index(align: usize) -> AlignIndex {
+    assert!(align.is_power_of_two(), "alignment must be a power of two");
+    let idx: usize =
+        align.ilog2().try_into().expect("alignment power should fit in usize");
+    AlignIndex::new(idx)
+        .expect("alignment is larger than any alignment Rust supports")
+}
+
+/// Returns the index of the slot for alignment `align` in this library's
+/// caches, i.e. its base-2 logarithm.
+///
+/// Every cache has one slot for each alignment from 1 up to the largest Rust
+/// supports, so the result is always less than [`CacheStats::slots`].
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of two, or is larger than any alignment
+/// Rust supports.
+pub fn alignment_index(align: usize) -> usize {
+    alignment_align_index(align).get()
+}
+
+/// Returns a new strong reference to the empty array cached in `slot`, the
+/// slot for alignment `2^idx`, as from `into_raw`.
+///
+/// Safety: `slot` must be the slot for alignment `2^idx`, and the cache's own
+/// reference must not be released until this returns.
+pub(crate) unsafe fn acquire<P: SharedPointer>(
+    slot: &impl Slot, idx: AlignIndex,
+) -> *const () {
+    struct Acquire<'a, P, S>(&'a S, PhantomData<P>);
+    impl<P: SharedPointer, S: Slot> WithAlign for Acquire<'_, P, S> {
+        type Output = *const ();
+        fn call<A>(self) -> *const () {
+            // Safety: `self.0` is the slot for `A`'s alignment.
+            let p = unsafe { get_or_make::<P, _>(self.0, P::new_empty::<A>) };
+            // Safety: `p` is kept alive by the cache's own reference.
+            unsafe { P::increment_strong(p) };
+            p.cast()
+        }
+    }
+
+    with_align_index(idx, Acquire::<P, _>(slot, PhantomData))
+}
+
+/// Returns a new weak reference to the empty array cached in `slot`, the slot
+/// for alignment `2^idx`, as from `Weak::into_raw`.
+///
+/// Safety: as for [`acquire`].
+pub(crate) unsafe fn acquire_weak<P: WeakPointer>(
+    slot: &impl Slot, idx: AlignIndex,
+) -> *const () {
+    struct AcquireWeak<'a, P, S>(&'a S, PhantomData<P>);
+    impl<P: WeakPointer, S: Slot> WithAlign for AcquireWeak<'_, P, S> {
+        type Output = *const ();
+        fn call<A>(self) -> *const () {
+            // Safety: `self.0` is the slot for `A`'s alignment.
+            let p = unsafe { get_or_make::<P, _>(self.0, P::new_empty::<A>) };
+            // Safety: `p` is kept alive by the cache's own reference.
+            unsafe { P::downgrade_raw(p) }.cast()
+        }
+    }
+
+    with_align_index(idx, AcquireWeak::<P, _>(slot, PhantomData))
+}
+
+/// Fills `slot`, the slot for alignment `2^idx`, if it is empty, and returns
+/// the cache's own reference it holds without incrementing it.
+///
+/// Safety: `slot` must be the slot for alignment `2^idx`.
+pub(crate) unsafe fn prewarm<P: SharedPointer>(
+    slot: &impl Slot, idx: AlignIndex,
+) -> *const () {
+    struct Prewarm<'a, P, S>(&'a S, PhantomData<P>);
+    impl<P: SharedPointer, S: Slot> WithAlign for Prewarm<'_, P, S> {
+        type Output = *const ();
+        fn call<A>(self) -> *const () {
+            // Safety: `self.0` is the slot for `A`'s alignment.
+            unsafe { get_or_make::<P, _>(self.0, P::new_empty::<A>) }.cast()
+        }
+    }
+
+    with_align_index(idx, Prewarm::<P, _>(slot, PhantomData))
+}
+
+/// Empties every slot, releasing the cache's own reference to each of their
+/// allocations.
+///
+/// Safety: `slots` must be a cache's slots, indexed by alignment, and nobody
+/// may be using a pointer loaded from them.
+pub(crate) unsafe fn clear<P: SharedPointer>(
+    slots: &[impl Slot; MAX_ALIGN_INDEX + 1],
+) {
+    struct Release<P>(*const (), PhantomData<P>);
+    impl<P: SharedPointer> WithAlign for Release<P> {
+        type Output = ();
+        fn call<A>(self) {
+            // Safety: see below.
+            unsafe { P::decrement_strong(self.0.cast::<[A; 0]>()) }
+        }
+    }
+
+    for idx in AlignIndex::all() {
+        let p = idx.slot(slots).take();
+        if !p.is_null() {
+            // Safety: `p` is the cache's own strong reference to an empty
+            // array whose element's alignment is 2^idx.
+            with_align_index(idx, Release::<P>(p, PhantomData));
+        }
+    }
+}
+
+pub(crate) fn stats(slots: &[impl Slot]) -> CacheStats {
+    let mut populated = 0;
+    let mut highest_index = None;
+    for (idx, slot) in slots.iter().enumerate() {
+        if !slot.load().is_null() {
+            populated += 1;
+            highest_index = Some(idx);
+        }
+    }
+    CacheStats { slots: slots.len(), populated, highest_index }
+}
+
+#[test]
+fn alignment_indices() {
+    assert_eq!(alignment_index(1), 0);
+    assert_eq!(alignment_index(2), 1);
+    assert_eq!(alignment_index(8), 3);
+    assert_eq!(alignment_index(4096), 12);
+    assert_eq!(alignment_index(1 << MAX_ALIGN_INDEX), MAX_ALIGN_INDEX);
+}
+
+#[test]
+#[should_panic = "alignment must be a power of two"]
+fn alignment_index_rejects_non_powers_of_two() {
+    alignment_index(12);
+}
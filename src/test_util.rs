@@ -0,0 +1,86 @@
+//! Helpers for testing code which uses this library.
+
+extern crate std;
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::alloc::System;
+
+/// A global allocator which counts the allocations and deallocations made
+/// through it, by every thread, and otherwise forwards to [`System`].
+///
+/// This can check that a program does not allocate once its empties have been
+/// cached:
+///
+/// ```
+/// use empty_rc_slice::{empty_arc_slice, CountingAllocator};
+/// use std::sync::Arc;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+///
+/// let _warm: Arc<[u32]> = empty_arc_slice();
+/// let before = ALLOCATOR.allocations();
+/// let _again: Arc<[u32]> = empty_arc_slice();
+/// # #[cfg(not(feature = "no-cache"))]
+/// assert_eq!(ALLOCATOR.allocations(), before);
+/// ```
+pub struct CountingAllocator {
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+}
+
+impl CountingAllocator {
+    /// Creates an allocator which has not counted anything yet.
+    pub const fn new() -> Self {
+        Self {
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of allocations made so far, including
+    /// reallocations.
+    pub fn allocations(&self) -> usize {
+        self.allocations.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of deallocations made so far, including
+    /// reallocations.
+    pub fn deallocations(&self) -> usize {
+        self.deallocations.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for CountingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(
+        &self, ptr: *mut u8, layout: Layout, new_size: usize,
+    ) -> *mut u8 {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
@@ -0,0 +1,146 @@
+//! A separate cache for [`triomphe::Arc`], whose allocations have a different
+//! layout from `alloc::sync::Arc`'s.
+
+use crate::{
+    align::MAX_ALIGN_INDEX,
+    shared::{self, try_align_index, SharedPointer},
+};
+use alloc::vec::Vec;
+use core::{mem::ManuallyDrop, ptr, sync::atomic::AtomicPtr};
+use triomphe::{Arc, ThinArc};
+
+/// The [`triomphe::Arc`] family of pointers.
+enum TriomphePointer {}
+
+impl SharedPointer for TriomphePointer {
+    type Strong<T: ?Sized> = Arc<T>;
+
+    /// `Arc::new([])` would build the whole allocation on the stack first, and
+    /// with a large alignment it can be larger than the stack.
+    fn new_empty<A>() -> Arc<[A; 0]> {
+        let slice: Arc<[A]> = Arc::from(Vec::new());
+        // Safety: `slice` has length 0, so it has the same layout as `[A; 0]`.
+        unsafe { Arc::from_raw(Arc::into_raw(slice).cast::<[A; 0]>()) }
+    }
+
+    fn into_raw<T: ?Sized>(this: Arc<T>) -> *const T {
+        Arc::into_raw(this)
+    }
+
+    unsafe fn from_raw<T>(ptr: *const T) -> Arc<T> {
+        unsafe { Arc::from_raw(ptr) }
+    }
+
+    unsafe fn increment_strong<T>(ptr: *const T) {
+        // `triomphe::Arc` has no `increment_strong_count`, so clone a borrowed
+        // reference and forget the clone.
+        let arc = ManuallyDrop::new(unsafe { Arc::from_raw(ptr) });
+        core::mem::forget(Arc::clone(&arc));
+    }
+
+    unsafe fn decrement_strong<T>(ptr: *const T) {
+        drop(unsafe { Arc::from_raw(ptr) });
+    }
+}
+
+const SLOTS: usize = MAX_ALIGN_INDEX + 1;
+
+// Each slot is either null or an `into_raw`'d `Arc<[U; 0]>` where U's
+// alignment is `2^idx`. Nothing ever releases the cache's own references, so
+// unlike the `Arc` cache this needs no lock.
+static RAWS: [AtomicPtr<()>; SLOTS] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; SLOTS];
+
+/// Returns a [`triomphe::Arc`] which points to an empty slice of `T`. This
+/// `Arc` may or may not share an allocation with other `triomphe::Arc`s
+/// returned from this library, including those pointing to other zero-sized
+/// types.
+///
+/// Unlike the `Arc` cache, this cache is never cleared. With the `no-cache`
+/// feature enabled, this always makes a new allocation.
+pub fn empty_triomphe_arc_slice<T>() -> Arc<[T]> {
+    // An alignment without a slot, which no current compiler allows, also
+    // gets a new allocation.
+    let idx =
+        try_align_index::<T>().ok().filter(|_| !cfg!(feature = "no-cache"));
+    let p = match idx {
+        Some(idx) => {
+            // Safety: `idx.slot(&RAWS)` is the slot for alignment `2^idx`,
+            // and the cache's own reference is never released.
+            unsafe { shared::acquire::<TriomphePointer>(idx.slot(&RAWS), idx) }
+        }
+        None => Arc::into_raw(TriomphePointer::new_empty::<T>()).cast::<()>(),
+    };
+    let slice = ptr::slice_from_raw_parts(p.cast::<T>(), 0);
+    // Safety: `p` is a new strong reference from `Arc::into_raw` on an
+    // `Arc<[U; 0]>` with `T`'s alignment, which has the same layout as an
+    // empty `Arc<[T]>`.
+    unsafe { Arc::from_raw_slice(slice) }
+}
+
+/// Returns a [`triomphe::Arc`] which points to an empty string slice. This
+/// shares an allocation with
+/// [`empty_triomphe_arc_slice::<u8>`](empty_triomphe_arc_slice).
+pub fn empty_triomphe_arc_str() -> Arc<str> {
+    let arc: Arc<[u8]> = empty_triomphe_arc_slice();
+    debug_assert!(arc.is_empty());
+    // Safety: `str` has the same layout as `[u8]`, and `arc` is empty, which
+    // is valid UTF-8.
+    unsafe { Arc::from_raw(Arc::into_raw(arc) as *const str) }
+}
+
+/// Returns a [`triomphe::ThinArc`] with a default header and an empty slice of
+/// `T`.
+///
+/// Unlike the other functions in this library, this makes a new allocation
+/// every time. The header is a value of a caller-chosen type, which may have
+/// interior mutability or be compared by address, so sharing one between
+/// unrelated callers could be observed, and there is no way to keep a cache
+/// per `H` without generic statics.
+pub fn empty_triomphe_thin_arc<H: Default, T>() -> ThinArc<H, T> {
+    ThinArc::from_header_and_iter(H::default(), core::iter::empty())
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn shares_per_alignment() {
+    #[repr(align(64))]
+    struct Align64;
+
+    let a: Arc<[u32]> = empty_triomphe_arc_slice();
+    let b: Arc<[u32]> = empty_triomphe_arc_slice();
+    assert!(a.is_empty());
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(Arc::as_ptr(&a).cast::<u32>().is_aligned());
+
+    let c: Arc<[Align64]> = empty_triomphe_arc_slice();
+    assert!(c.is_empty());
+    assert!(Arc::as_ptr(&c).cast::<Align64>().is_aligned());
+    assert!(Arc::ptr_eq(&c, &empty_triomphe_arc_slice()));
+
+    let s = empty_triomphe_arc_str();
+    assert_eq!(&*s, "");
+    let bytes: Arc<[u8]> = empty_triomphe_arc_slice();
+    assert!(ptr::addr_eq(Arc::as_ptr(&s), Arc::as_ptr(&bytes)));
+}
+
+#[test]
+fn drops_balance() {
+    let a: Arc<[u64]> = empty_triomphe_arc_slice();
+    let before = Arc::strong_count(&a);
+    let b: Arc<[u64]> = empty_triomphe_arc_slice();
+    drop(b);
+    assert_eq!(Arc::strong_count(&a), before);
+}
+
+#[test]
+fn thin_arc() {
+    let a: ThinArc<u32, u64> = empty_triomphe_thin_arc();
+    assert_eq!(a.header.header, 0);
+    assert_eq!(a.header.length, 0);
+    assert!(a.slice.is_empty());
+    // Clones of one `ThinArc` share as usual, but separate calls do not.
+    let b = a.clone();
+    assert!(core::ptr::eq(&*a, &*b));
+    assert_eq!(ThinArc::strong_count(&a), 2);
+}
@@ -0,0 +1,76 @@
+#![cfg(all(feature = "arc-module", not(feature = "no-cache")))]
+
+use empty_rc_slice::empty_arc_slice;
+use std::sync::Arc;
+
+macro_rules! wrapper_types {
+    ($($align:literal => $bytes:ident, $byte:ident;)*) => {
+        $(
+            #[repr(align($align))]
+            struct $bytes(#[allow(dead_code)] [u8; $align]);
+
+            #[repr(align($align))]
+            struct $byte(#[allow(dead_code)] u8);
+        )*
+
+        /// For each wrapper alignment, the addresses of the empty slices of
+        /// both of its wrapper types.
+        fn addresses() -> Vec<(usize, usize, usize)> {
+            vec![$({
+                let a: Arc<[$bytes]> = empty_arc_slice();
+                let b: Arc<[$byte]> = empty_arc_slice();
+                assert!(a.is_empty());
+                assert!(b.is_empty());
+                assert!(Arc::as_ptr(&a).cast::<$bytes>().is_aligned());
+                (
+                    $align,
+                    Arc::as_ptr(&a).cast::<()>() as usize,
+                    Arc::as_ptr(&b).cast::<()>() as usize,
+                )
+            },)*]
+        }
+    };
+}
+
+wrapper_types! {
+    2 => Bytes2, Byte2;
+    4 => Bytes4, Byte4;
+    8 => Bytes8, Byte8;
+    16 => Bytes16, Byte16;
+    32 => Bytes32, Byte32;
+    64 => Bytes64, Byte64;
+    128 => Bytes128, Byte128;
+    256 => Bytes256, Byte256;
+    512 => Bytes512, Byte512;
+    1024 => Bytes1024, Byte1024;
+    2048 => Bytes2048, Byte2048;
+    4096 => Bytes4096, Byte4096;
+}
+
+#[test]
+fn same_alignment_shares() {
+    for (align, a, b) in addresses() {
+        assert_eq!(a, b, "alignment {align}");
+    }
+}
+
+#[test]
+fn different_alignments_do_not_share() {
+    let addresses = addresses();
+    for (i, &(align, a, _)) in addresses.iter().enumerate() {
+        for &(other, b, _) in &addresses[i + 1..] {
+            assert_ne!(a, b, "alignments {align} and {other}");
+        }
+    }
+}
+
+#[test]
+fn simd_shares_with_primitives_of_its_alignment() {
+    #[repr(align(32))]
+    struct Simd(#[allow(dead_code)] [f32; 8]);
+
+    let simd: Arc<[Simd]> = empty_arc_slice();
+    let bytes: Arc<[Bytes32]> = empty_arc_slice();
+    assert!(Arc::as_ptr(&simd).cast::<Simd>().is_aligned());
+    assert!(std::ptr::addr_eq(Arc::as_ptr(&simd), Arc::as_ptr(&bytes)));
+}
@@ -0,0 +1,153 @@
+#![cfg(all(feature = "arc-module", not(feature = "no-cache")))]
+
+mod common;
+
+use common::{live_allocations, CountingAllocator};
+use empty_rc_slice::*;
+use std::{
+    sync::{Arc, Barrier, Mutex, MutexGuard},
+    thread,
+};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The tests in this file each modify the whole cache, so they must not run
+/// concurrently.
+fn serial() -> MutexGuard<'static, ()> {
+    static SERIAL: Mutex<()> = Mutex::new(());
+    SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+fn clear_arc_cache_frees_allocations() {
+    let _serial = serial();
+    clear_arc_cache();
+    let baseline = live_allocations();
+
+    let a: Arc<[u32]> = empty_arc_slice();
+    let b: Arc<[u32]> = empty_arc_slice();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(live_allocations(), baseline + 1);
+
+    drop((a, b));
+    // The cache still holds the allocation.
+    assert_eq!(live_allocations(), baseline + 1);
+
+    let c: Arc<[u32]> = empty_arc_slice();
+    clear_arc_cache();
+    // User handles keep the allocation alive past clearing.
+    assert_eq!(live_allocations(), baseline + 1);
+    assert_eq!(c.len(), 0);
+    drop(c);
+    assert_eq!(live_allocations(), baseline);
+}
+
+#[test]
+fn arc_cache_slots_are_fixed() {
+    #[repr(align(1024))]
+    struct Align1024;
+
+    let _serial = serial();
+    let slots = arc_cache_stats().slots;
+    let _low: Arc<[u8]> = empty_arc_slice();
+    let _high: Arc<[Align1024]> = empty_arc_slice();
+    // Caching an allocation never needs to grow the cache.
+    assert_eq!(arc_cache_stats().slots, slots);
+
+    clear_arc_cache();
+    assert_eq!(arc_cache_stats().slots, slots);
+}
+
+#[test]
+fn concurrent_first_use_allocates_once() {
+    #[repr(align(256))]
+    struct Align256;
+
+    let _serial = serial();
+    clear_arc_cache();
+    let threads = if cfg!(miri) { 4 } else { 16 };
+    let barrier = Arc::new(Barrier::new(threads));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                // Allocations are counted per thread, so each thread reports
+                // how many of its own allocations outlive its call.
+                barrier.wait();
+                let baseline = live_allocations();
+                let a: Arc<[Align256]> = empty_arc_slice();
+                (live_allocations() - baseline, a)
+            })
+        })
+        .collect();
+    let results: Vec<_> =
+        handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    // Threads which lost the race freed their own allocation again.
+    assert_eq!(results.iter().map(|(live, _)| live).sum::<isize>(), 1);
+    assert!(results.iter().all(|(_, a)| Arc::ptr_eq(a, &results[0].1)));
+}
+
+#[test]
+fn arc_cache_only_allocates_empty_arrays() {
+    #[repr(align(128))]
+    struct Align128;
+
+    let _serial = serial();
+    clear_arc_cache();
+    let baseline = live_allocations();
+
+    // The first use of an alignment makes exactly one allocation, and none
+    // for the cache itself.
+    let a: Arc<[Align128]> = empty_arc_slice();
+    assert_eq!(live_allocations(), baseline + 1);
+    let b: Arc<[Align128]> = empty_arc_slice();
+    let c: Arc<[u8]> = empty_arc_slice();
+    assert_eq!(live_allocations(), baseline + 2);
+
+    drop((a, b, c));
+    clear_arc_cache();
+    assert_eq!(live_allocations(), baseline);
+}
+
+#[test]
+fn arc_cache_high_water_is_an_alignment() {
+    #[repr(align(16))]
+    struct Align16;
+
+    let _serial = serial();
+    clear_arc_cache();
+    assert_eq!(arc_cache_high_water(), None);
+    let _a: Arc<[u8]> = empty_arc_slice();
+    let _b: Arc<[Align16]> = empty_arc_slice();
+    assert_eq!(arc_cache_high_water(), Some(16));
+    clear_arc_cache();
+    assert_eq!(arc_cache_high_water(), None);
+}
+
+#[test]
+fn arc_alloc_hook_fires_on_new_allocations() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static U64_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    fn hook(align: usize) {
+        if align == std::mem::align_of::<u64>() {
+            U64_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let _serial = serial();
+    clear_arc_cache();
+    set_arc_alloc_hook(hook);
+    let a: Arc<[u64]> = empty_arc_slice();
+    let b: Arc<[u64]> = empty_arc_slice();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(U64_ALLOCATIONS.load(Ordering::Relaxed), 1);
+
+    assert!(take_arc_alloc_hook().is_some());
+    assert!(take_arc_alloc_hook().is_none());
+    clear_arc_cache();
+    let _c: Arc<[u64]> = empty_arc_slice();
+    assert_eq!(U64_ALLOCATIONS.load(Ordering::Relaxed), 1);
+}
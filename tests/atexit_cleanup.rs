@@ -0,0 +1,35 @@
+#![cfg(all(feature = "atexit-cleanup", not(feature = "no-cache")))]
+
+use empty_rc_slice::*;
+use std::{process::Command, sync::Arc};
+
+const CHILD: &str = "EMPTY_RC_SLICE_ATEXIT_CHILD";
+
+/// Registered before the library's teardown, so it runs after it.
+extern "C" fn check_cleared() {
+    let cleared = arc_cache_stats().populated == 0;
+    // Safety: exiting immediately from an exit handler is fine.
+    unsafe { libc::_exit(if cleared { 0 } else { 1 }) }
+}
+
+#[test]
+fn teardown_runs_at_exit() {
+    if std::env::var_os(CHILD).is_none() {
+        // Exit handlers only run when the process exits, so check them from a
+        // child process running just this test.
+        let status = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "teardown_runs_at_exit", "--test-threads=1"])
+            .env(CHILD, "1")
+            .status()
+            .unwrap();
+        assert!(status.success(), "{status}");
+        return;
+    }
+
+    // Safety: `check_cleared` does not unwind.
+    assert_eq!(unsafe { libc::atexit(check_cleared) }, 0);
+    let a: Arc<[u64]> = empty_arc_slice();
+    drop(a);
+    assert_eq!(arc_cache_stats().populated, 1);
+    std::process::exit(2);
+}
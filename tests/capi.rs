@@ -0,0 +1,29 @@
+#![cfg(all(feature = "capi", not(feature = "no-cache")))]
+
+use empty_rc_slice::{
+    capi::{empty_arc_u8_slice, empty_arc_u8_slice_release},
+    empty_arc_slice,
+};
+use std::{ptr, sync::Arc};
+
+#[test]
+fn u8_slice_refcount_balances() {
+    // Nothing else in this binary uses the slot for `u8`'s alignment.
+    let held: Arc<[u8]> = empty_arc_slice();
+    let before = Arc::strong_count(&held);
+
+    let mut ptrs = [ptr::null(); 3];
+    for p in &mut ptrs {
+        let mut len = usize::MAX;
+        unsafe { empty_arc_u8_slice(p, &mut len) };
+        assert!(!p.is_null());
+        assert_eq!(*p, Arc::as_ptr(&held).cast::<u8>());
+        assert_eq!(len, 0);
+    }
+    assert_eq!(Arc::strong_count(&held), before + ptrs.len());
+
+    for p in ptrs {
+        unsafe { empty_arc_u8_slice_release(p) };
+    }
+    assert_eq!(Arc::strong_count(&held), before);
+}
@@ -0,0 +1,73 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering},
+};
+
+/// Counts the allocations made by each thread which have not been freed by
+/// the same thread, so that tests running in parallel do not disturb each
+/// other.
+pub struct CountingAllocator;
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+}
+
+/// The alignment whose allocations are also counted across every thread, for
+/// allocations freed after the allocating thread's counter is destroyed.
+#[allow(dead_code)]
+pub const TRACKED_ALIGN: usize = 8192;
+
+static TRACKED_LIVE: AtomicIsize = AtomicIsize::new(0);
+
+static WATCHED: AtomicUsize = AtomicUsize::new(0);
+static WATCHED_FREED: AtomicBool = AtomicBool::new(false);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = LIVE.try_with(|live| live.set(live.get() + 1));
+        if layout.align() == TRACKED_ALIGN {
+            TRACKED_LIVE.fetch_add(1, Ordering::Relaxed);
+        }
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE.try_with(|live| live.set(live.get() - 1));
+        if layout.align() == TRACKED_ALIGN {
+            TRACKED_LIVE.fetch_sub(1, Ordering::Relaxed);
+        }
+        let watched = WATCHED.load(Ordering::Relaxed);
+        if (ptr.addr()..ptr.addr() + layout.size()).contains(&watched) {
+            WATCHED_FREED.store(true, Ordering::Relaxed);
+        }
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Returns the number of allocations the current thread has made and not
+/// freed.
+pub fn live_allocations() -> isize {
+    LIVE.get()
+}
+
+/// Returns the number of allocations with alignment [`TRACKED_ALIGN`] which
+/// any thread has made and not freed.
+#[allow(dead_code)]
+pub fn tracked_live_allocations() -> isize {
+    TRACKED_LIVE.load(Ordering::Relaxed)
+}
+
+/// Watches the allocation containing `addr`, which may be freed by any
+/// thread. Only one allocation can be watched at a time.
+#[allow(dead_code)]
+pub fn watch(addr: usize) {
+    WATCHED_FREED.store(false, Ordering::Relaxed);
+    WATCHED.store(addr, Ordering::Relaxed);
+}
+
+/// Returns whether the allocation passed to [`watch`] has been freed.
+#[allow(dead_code)]
+pub fn watched_freed() -> bool {
+    WATCHED_FREED.load(Ordering::Relaxed)
+}
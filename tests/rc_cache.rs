@@ -0,0 +1,88 @@
+#![cfg(all(feature = "rc-module", not(feature = "no-cache")))]
+
+mod common;
+
+use common::{
+    live_allocations, tracked_live_allocations, watch, watched_freed,
+    CountingAllocator, TRACKED_ALIGN,
+};
+use empty_rc_slice::*;
+use std::{rc::Rc, thread};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn clear_rc_cache_frees_allocations() {
+    thread::spawn(|| {
+        let baseline = live_allocations();
+
+        let a: Rc<[u32]> = empty_rc_slice();
+        let b: Rc<[u16]> = empty_rc_slice();
+        assert_eq!(live_allocations(), baseline + 2);
+
+        clear_rc_cache();
+        // User handles keep the allocations alive past clearing.
+        assert_eq!(live_allocations(), baseline + 2);
+        drop(a);
+        assert_eq!(live_allocations(), baseline + 1);
+        drop(b);
+        assert_eq!(live_allocations(), baseline);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn rc_cache_only_allocates_empty_arrays() {
+    #[repr(align(4096))]
+    struct Align4096;
+
+    thread::spawn(|| {
+        let baseline = live_allocations();
+
+        // The first use on this thread makes exactly one allocation per
+        // alignment, and none for the cache itself.
+        let a: Rc<[u8]> = empty_rc_slice();
+        assert_eq!(live_allocations(), baseline + 1);
+        let b: Rc<[Align4096]> = empty_rc_slice();
+        assert_eq!(live_allocations(), baseline + 2);
+
+        // Later uses share them.
+        assert!(Rc::ptr_eq(&a, &empty_rc_slice()));
+        assert!(Rc::ptr_eq(&b, &empty_rc_slice()));
+        let c: Rc<[bool]> = empty_rc_slice();
+        assert_eq!(live_allocations(), baseline + 2);
+
+        drop((a, b, c));
+        clear_rc_cache();
+        assert_eq!(live_allocations(), baseline);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn rc_cache_is_freed_at_thread_exit() {
+    #[repr(align(8192))]
+    struct Align8192;
+    assert_eq!(std::mem::align_of::<Align8192>(), TRACKED_ALIGN);
+
+    // Only this test allocates at the tracked alignment, and the cache's
+    // allocation is freed by a thread-local destructor, after the thread's own
+    // counter may already be gone.
+    assert_eq!(tracked_live_allocations(), 0);
+    thread::spawn(|| {
+        let a: Rc<[Align8192]> = empty_rc_slice();
+        assert!(Rc::ptr_eq(&a, &empty_rc_slice()));
+        assert_eq!(tracked_live_allocations(), 1);
+
+        // The empty C string has its own allocation.
+        let c = empty_rc_cstr();
+        watch(Rc::as_ptr(&c).cast::<u8>().addr());
+    })
+    .join()
+    .unwrap();
+    assert_eq!(tracked_live_allocations(), 0);
+    assert!(watched_freed());
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "serde")]
+
+use empty_rc_slice::{deserialize_empty_arc_slice, is_cached_arc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+struct Row {
+    #[serde(deserialize_with = "deserialize_empty_arc_slice")]
+    values: Arc<[u32]>,
+}
+
+#[cfg(not(feature = "no-cache"))]
+#[test]
+fn empty_sequence_is_shared() {
+    let a: Row = serde_json::from_str(r#"{ "values": [] }"#).unwrap();
+    let b: Row = serde_json::from_str(r#"{ "values": [] }"#).unwrap();
+    assert!(a.values.is_empty());
+    assert!(is_cached_arc(&a.values));
+    assert!(Arc::ptr_eq(&a.values, &b.values));
+}
+
+#[test]
+fn non_empty_sequence_deserializes_normally() {
+    let row: Row = serde_json::from_str(r#"{ "values": [1, 2, 3] }"#).unwrap();
+    assert_eq!(*row.values, [1, 2, 3]);
+    assert!(!is_cached_arc(&row.values));
+    assert!(serde_json::from_str::<Row>(r#"{ "values": 1 }"#).is_err());
+}
+
+/// A sequence of one element which claims to be far longer, like a malicious
+/// length prefix.
+struct LyingSeq(Option<u32>);
+
+impl<'de> serde::de::SeqAccess<'de> for LyingSeq {
+    type Error = serde::de::value::Error;
+
+    fn next_element_seed<S: serde::de::DeserializeSeed<'de>>(
+        &mut self, seed: S,
+    ) -> Result<Option<S::Value>, Self::Error> {
+        self.0
+            .take()
+            .map(|x| {
+                seed.deserialize(
+                    serde::de::IntoDeserializer::into_deserializer(x),
+                )
+            })
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(usize::MAX)
+    }
+}
+
+struct LyingDeserializer;
+
+impl<'de> serde::Deserializer<'de> for LyingDeserializer {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self, visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(LyingSeq(Some(7)))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[test]
+fn huge_size_hint_is_not_trusted() {
+    let values: Arc<[u64]> =
+        deserialize_empty_arc_slice(LyingDeserializer).unwrap();
+    assert_eq!(*values, [7]);
+}
@@ -0,0 +1,184 @@
+#![cfg(all(
+    feature = "shared-symbol",
+    not(feature = "no-cache"),
+    target_os = "linux"
+))]
+
+use std::{ffi::CStr, fs, path::Path, process::Command};
+
+const FIXTURE_MANIFEST: &str = r#"[package]
+name = "shared-symbol-fixture"
+version = "0.0.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib"]
+
+[dependencies.empty-rc-slice]
+path = "{path}"
+default-features = false
+features = ["shared-symbol", "{backend}"]
+
+[workspace]
+"#;
+
+const FIXTURE_LIB: &str = r#"
+#[no_mangle]
+pub extern "C" fn fixture_empty_u64_slice() -> *const () {
+    std::sync::Arc::into_raw(empty_rc_slice::empty_arc_slice::<u64>()).cast()
+}
+"#;
+
+/// Exports the `spin` backend's symbol with a zeroed tag, as if from a copy
+/// of this library whose cache has a different layout.
+const MISMATCHED_MANIFEST: &str = r#"[package]
+name = "shared-symbol-fixture"
+version = "0.0.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib"]
+
+[workspace]
+"#;
+
+const MISMATCHED_LIB: &str = r#"
+#[export_name = "empty_rc_slice_arc_cache_v0_1_spin"]
+pub static MISMATCHED: [usize; 64] = [0; 64];
+"#;
+
+/// Builds a `cdylib` from `manifest` and `lib` in `dir`, returning the path of
+/// the built library.
+fn build(dir: &Path, manifest: &str, lib: &str) -> std::path::PathBuf {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("Cargo.toml"), manifest.replace("{path}", manifest_dir))
+        .unwrap();
+    fs::write(dir.join("src/lib.rs"), lib).unwrap();
+    // Use the same dependency versions as this library's own tests.
+    fs::copy(
+        Path::new(manifest_dir).join("Cargo.lock"),
+        dir.join("Cargo.lock"),
+    )
+    .unwrap();
+
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = Command::new(cargo)
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(dir.join("target"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "{status}");
+    dir.join("target/debug/libshared_symbol_fixture.so")
+}
+
+/// Builds a `cdylib` which statically links this library with the lock
+/// backend feature `backend`, returning the path of the built library.
+fn build_fixture(dir: &Path, backend: &str) -> std::path::PathBuf {
+    build(dir, &FIXTURE_MANIFEST.replace("{backend}", backend), FIXTURE_LIB)
+}
+
+/// Copies `built` to two files in its fixture's directory, so that the
+/// dynamic loader loads them as two separate libraries, each with its own
+/// copy of this library.
+fn two_copies(
+    dir: &Path, built: &Path,
+) -> (std::path::PathBuf, std::path::PathBuf) {
+    let a = dir.join("liba.so");
+    let b = dir.join("libb.so");
+    fs::copy(built, &a).unwrap();
+    fs::copy(built, &b).unwrap();
+    (a, b)
+}
+
+/// Loads the library at `path` so that its symbols are visible to the whole
+/// process, and returns its handle and its function returning the address of
+/// `empty_arc_slice::<u64>()`.
+fn load(path: &Path) -> (*mut libc::c_void, extern "C" fn() -> *const ()) {
+    let path =
+        std::ffi::CString::new(path.as_os_str().as_encoded_bytes()).unwrap();
+    // Safety: the fixture's initializers are only the Rust runtime's.
+    let handle = unsafe {
+        libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL)
+    };
+    assert!(!handle.is_null());
+    let name: &CStr = c"fixture_empty_u64_slice";
+    // Safety: `name` is NUL-terminated.
+    let f = unsafe { libc::dlsym(handle, name.as_ptr()) };
+    assert!(!f.is_null());
+    // Safety: the fixture defines the symbol with this signature.
+    let f = unsafe {
+        std::mem::transmute::<*mut libc::c_void, extern "C" fn() -> *const ()>(
+            f,
+        )
+    };
+    (handle, f)
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn dylibs_share_one_cache() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("shared-symbol");
+    let built = build_fixture(&dir, "std-sync");
+    let (a, b) = two_copies(&dir, &built);
+
+    let (handle_a, empty_a) = load(&a);
+    let (_, empty_b) = load(&b);
+    let from_a = empty_a();
+    let from_b = empty_b();
+    assert!(!from_a.is_null());
+    assert_eq!(from_a, from_b);
+
+    // The first library's cache is the shared one, so it must stay loaded for
+    // the second to keep using it.
+    // Safety: `handle_a` is from `dlopen`, and is not used again.
+    assert_eq!(unsafe { libc::dlclose(handle_a) }, 0);
+    let path =
+        std::ffi::CString::new(a.as_os_str().as_encoded_bytes()).unwrap();
+    // Safety: with `RTLD_NOLOAD`, this only looks up the loaded library.
+    let still_loaded = unsafe {
+        libc::dlopen(path.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD)
+    };
+    assert!(!still_loaded.is_null());
+    assert_eq!(empty_b(), from_a);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mismatched_tag_is_not_shared() {
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let mismatched = build(
+        &tmp.join("shared-symbol-mismatched"),
+        MISMATCHED_MANIFEST,
+        MISMATCHED_LIB,
+    );
+    // This test uses the `spin` backend, whose symbol no other test exports,
+    // so the mismatched one is the first visible to the whole process.
+    let dir = tmp.join("shared-symbol-spin");
+    let built = build_fixture(&dir, "spin");
+    let (a, b) = two_copies(&dir, &built);
+
+    let path =
+        std::ffi::CString::new(mismatched.as_os_str().as_encoded_bytes())
+            .unwrap();
+    // Safety: the library has no initializers.
+    let handle = unsafe {
+        libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL)
+    };
+    assert!(!handle.is_null());
+
+    let (_, empty_a) = load(&a);
+    let (_, empty_b) = load(&b);
+    let from_a = empty_a();
+    let from_b = empty_b();
+    assert!(!from_a.is_null());
+    assert!(!from_b.is_null());
+    // Each copy falls back to its own cache, which it still shares with
+    // itself.
+    assert_ne!(from_a, from_b);
+    assert_eq!(empty_a(), from_a);
+    assert_eq!(empty_b(), from_b);
+}
@@ -0,0 +1,26 @@
+#![cfg(all(
+    feature = "test-util",
+    feature = "arc-module",
+    not(feature = "no-cache")
+))]
+
+use empty_rc_slice::{clear_arc_cache, empty_arc_slice, CountingAllocator};
+use std::sync::Arc;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+
+// This is the only test in this binary, so nothing else allocates while it
+// runs.
+#[test]
+fn empty_arc_slice_allocates_once() {
+    clear_arc_cache();
+    let before = ALLOCATOR.allocations();
+    let first: Arc<[u32]> = empty_arc_slice();
+    assert_eq!(ALLOCATOR.allocations(), before + 1);
+    for _ in 0..1000 {
+        let again: Arc<[u32]> = empty_arc_slice();
+        assert!(Arc::ptr_eq(&first, &again));
+    }
+    assert_eq!(ALLOCATOR.allocations(), before + 1);
+}
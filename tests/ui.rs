@@ -0,0 +1,10 @@
+#![cfg(feature = "arc-module")]
+
+#[test]
+#[cfg_attr(miri, ignore = "runs the compiler")]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/array_n_zero.rs");
+    t.compile_fail("tests/ui/array_n_nonzero.rs");
+    t.compile_fail("tests/ui/cast_mismatched_align.rs");
+}
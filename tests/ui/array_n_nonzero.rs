@@ -0,0 +1,6 @@
+use empty_rc_slice::empty_arc_array_n;
+use std::sync::Arc;
+
+fn main() {
+    let _: Arc<[u8; 1]> = empty_arc_array_n::<u8, 1>();
+}
@@ -0,0 +1,7 @@
+use empty_rc_slice::empty_arc_array_n;
+use std::sync::Arc;
+
+fn main() {
+    let a: Arc<[u8; 0]> = empty_arc_array_n::<u8, 0>();
+    assert!(a.is_empty());
+}
@@ -0,0 +1,6 @@
+use empty_rc_slice::{cast_empty_arc, empty_arc_slice};
+use std::sync::Arc;
+
+fn main() {
+    let _: Result<Arc<[u64]>, _> = cast_empty_arc(empty_arc_slice::<u8>());
+}